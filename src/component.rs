@@ -5,20 +5,31 @@ use crate::{
         *
     },
     error::{Error, Result},
-    link::Link,
-    mission::IntoMissionItem,
+    health::Health,
+    link::{Link, LinkSender},
+    mission::{
+        Geofence, IntoMissionItem, MissionError, MissionEvent, MissionItem, MissionTracker, MissionUpload,
+        RallyPoint, UploadItem, UploadProgress,
+    },
+    param::{param_id, Param, ParamEncoding, ParamValue},
+    signing::{SecretKey, Signing},
+    telemetry::Battery,
     wire::Packet,
 };
 
 use std::{
-    sync::Arc,
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
     result::Result as StdResult,
     pin::Pin,
     task::{Poll, Context}
 };
 
-use futures_util::{pin_mut, future::{Future, Ready, ready}, Stream, StreamExt};
+use futures_util::{pin_mut, future::{Future, FutureExt, Ready, ready}, stream, Stream, StreamExt};
 use futures_time::{
     stream::StreamExt as FuturesTimeStreamExt,
     time::Duration as FuturesTimeDuration,
@@ -36,16 +47,56 @@ pub struct Component {
     id: u8,
     system: u8,
     link: Link,
+    param_encoding: ParamEncoding,
 }
 
 impl Component {
     pub fn new(id: u8, system: u8, link: Link) -> Self {
-        Self { id, system, link }
+        Self { id, system, link, param_encoding: ParamEncoding::default() }
+    }
+
+    // Keeps only the sending half of the link, for holders that receive
+    // through a new component when they need to.
+    pub(crate) fn sender(&self) -> ComponentSender {
+        ComponentSender {
+            id: self.id,
+            system: self.system,
+            link: self.link.sender(),
+            param_encoding: self.param_encoding,
+        }
+    }
+
+    pub fn param_encoding(&self) -> ParamEncoding {
+        self.param_encoding
+    }
+
+    /// Sets how the component encodes integer parameters, which is
+    /// [`ParamEncoding::CCast`] by default, see
+    /// [`Component::detect_param_encoding`].
+    pub fn set_param_encoding(&mut self, encoding: ParamEncoding) {
+        self.param_encoding = encoding;
+    }
+
+    /// Picks how the component encodes integer parameters from the next
+    /// `HEARTBEAT` or `AUTOPILOT_VERSION` that it sends, and returns it.
+    ///
+    /// The capabilities of `AUTOPILOT_VERSION` are used when they tell the
+    /// encoding, the autopilot of `HEARTBEAT` otherwise. The encoding is kept
+    /// if neither arrives in time.
+    pub async fn detect_param_encoding(&mut self, timeout: Duration) -> Result<ParamEncoding> {
+        let encoding = self.wait_for(|packet| match &packet.message {
+            Message::HEARTBEAT(heartbeat) => Some(ParamEncoding::from_autopilot(heartbeat.autopilot)),
+            Message::AUTOPILOT_VERSION(version) => ParamEncoding::from_capabilities(version.capabilities),
+            _ => None,
+        }, timeout).await?;
+
+        self.param_encoding = encoding;
+        Ok(encoding)
     }
 
     pub fn try_recv(&mut self) -> StdResult<Arc<Packet>, TryRecvError> {
         loop {
-            let packet = self.link.subscriber.try_recv()?;
+            let packet = self.link.try_recv()?;
             let Header { system_id, component_id, .. } = packet.header;
 
             if system_id == self.system && component_id == self.id {
@@ -71,6 +122,23 @@ impl Component {
             .or(Err(Error::Timeout))
     }
 
+    // Sends the message until a packet that the predicate maps to a value is
+    // received.
+    async fn request<T, F>(&mut self, message: Message, mut predicate: F) -> Result<T>
+    where F: FnMut(&Packet) -> Option<T>
+    {
+        for _ in 0..MAX_RETRY {
+            self.link.send_message(message.clone()).await?;
+
+            match self.wait_for(&mut predicate, ACK_TIMEOUT).await {
+                Err(Error::Timeout) => {}
+                other => return other,
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+
     async fn probe<T, F, Fut>(&mut self, mut f: F, dur: Duration, mut retry: usize) -> Result<T>
     where F: FnMut(Arc<Packet>)-> Fut,
           Fut: Future<Output = Option<T>>
@@ -85,25 +153,23 @@ impl Component {
         Err(Error::Timeout)
     }
 
-    // TODO: We need packet routing (target system id matches, blah blah).
     pub async fn command_int(&mut self, mut command: CommandInt) -> Result<MavResult> {
         command.target_system = self.system;
         command.target_component = self.id;
 
-        let ref filter = ack_filter(command.command);
+        let filter = &ack_filter(&self.link, command.command);
 
         self.link.send_message(Message::COMMAND_INT(command)).await?;
         self.probe(filter, ACK_TIMEOUT, MAX_RETRY).await
     }
 
-    // TODO: We need packet routing (target system id matches, blah blah).
     pub async fn command_long(&mut self, mut command: CommandLong) -> Result<MavResult> {
         // Slap the target address.
         command.target_system = self.system;
         command.target_component = self.id;
 
         // Create a filter for ack commands that will catch the current command.
-        let ref filter = ack_filter(command.command);
+        let filter = &ack_filter(&self.link, command.command);
         let message = Message::COMMAND_LONG(command);
         let mut confirmation = 0;
 
@@ -141,48 +207,443 @@ impl Component {
         }).await
     }
 
-    pub async fn upload_mission<M, I>(&mut self, mission: M) -> Result<MavMissionResult>
+    /// Uploads the mission, which is the same as
+    /// [`Component::start_mission_upload`] followed by
+    /// [`MissionUpload::finish`].
+    pub async fn upload_mission<M, I>(&mut self, mission: M) -> StdResult<(), MissionError>
     where
         M: AsRef<[I]>,
         I: IntoMissionItem,
     {
-        let items = mission.as_ref();
+        self.start_mission_upload(mission).finish().await
+    }
+
+    /// Starts uploading the mission, and returns the upload, which reports
+    /// its progress and can be cancelled.
+    ///
+    /// The upload runs while it is polled, as a stream or with
+    /// [`MissionUpload::finish`].
+    pub fn start_mission_upload<M, I>(&self, mission: M) -> MissionUpload
+    where
+        M: AsRef<[I]>,
+        I: IntoMissionItem,
+    {
+        self.start_upload(MavMissionType::MAV_MISSION_TYPE_MISSION, mission.as_ref())
+    }
+
+    fn start_upload<I: IntoMissionItem>(&self, mission_type: MavMissionType, items: &[I]) -> MissionUpload {
+        // The items are owned by the upload.
+        let items: Vec<_> = items.iter().map(UploadItem::new).collect();
+        let (progress_sender, progress) = flume::unbounded();
+        let started = Arc::new(AtomicBool::new(false));
+        let sender = self.sender();
+
+        // Subscribe on the first poll, an upload that is created but not
+        // polled yet would hold up a link with back pressure.
+        let counted = started.clone();
+        let upload = async move {
+            sender.component().upload_items(mission_type, &items, &counted, |progress| {
+                let _ = progress_sender.send(progress);
+            }).await
+        };
+
+        let cancel = Message::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: self.system,
+            target_component: self.id,
+            mavtype: MavMissionResult::MAV_MISSION_OPERATION_CANCELLED,
+            mission_type,
+        });
+
+        MissionUpload::new(upload.boxed(), progress, started, self.link.sender(), cancel)
+    }
+
+    // Runs the upload side of the mission protocol, which succeeds when the
+    // component accepts the items. `started` is set once the count is sent,
+    // from then on the component waits for the items.
+    async fn upload_items<I, F>(
+        &mut self,
+        mission_type: MavMissionType,
+        items: &[I],
+        started: &AtomicBool,
+        mut report: F,
+    ) -> StdResult<(), MissionError>
+    where
+        I: IntoMissionItem,
+        F: FnMut(UploadProgress),
+    {
+        let total = items.len();
+        let count = u16::try_from(total).or(Err(MissionError::TooManyItems(total)))?;
+        let addressed_to_us = addressed_to(&self.link);
 
         let mission_count = Message::MISSION_COUNT(MISSION_COUNT_DATA {
-            count: items.len() as u16,
+            count,
             target_system: self.system,
             target_component: self.id,
-            mission_type: MavMissionType::MAV_MISSION_TYPE_MISSION,
+            mission_type,
         });
 
-        self.link.send_message(mission_count).await?;
+        let mut progress = UploadProgress { sent: 0, total, retries: 0 };
+        let mut sent = vec![false; total];
+        let mut requested = false;
+        let mut timeouts = 0;
+
+        // The packets that were received before the upload, such as the late
+        // ack of an earlier one, are not responses to it.
+        while self.link.try_recv().is_ok() {}
+
+        self.link.send_message(mission_count.clone()).await?;
+        started.store(true, Ordering::Relaxed);
+
+        loop {
+            let response = self.wait_for(|packet| {
+                let response = match &packet.message {
+                    Message::MISSION_REQUEST_INT(req) if req.mission_type == mission_type => {
+                        UploadResponse::Request { seq: req.seq, int: true }
+                    }
+                    Message::MISSION_REQUEST(req) if req.mission_type == mission_type => {
+                        UploadResponse::Request { seq: req.seq, int: false }
+                    }
+                    Message::MISSION_ACK(ack) if ack.mission_type == mission_type => UploadResponse::Ack(ack.mavtype),
+                    _ => return None,
+                };
 
-        let mission_result = loop {
-            let packet = self.probe(mission_filter, ACK_TIMEOUT, MAX_RETRY).await?;
+                addressed_to_us(packet).then_some(response)
+            }, ACK_TIMEOUT).await;
 
-            match &packet.message {
-                Message::MISSION_REQUEST(req) => {
-                    let item = items[req.seq as usize].with(self.system, self.id, req.seq);
+            match response {
+                Ok(UploadResponse::Request { seq, int }) => {
+                    requested = true;
+                    timeouts = 0;
 
-                    let mission_item = Message::MISSION_ITEM(item);
-                    self.link.send_message(mission_item).await?;
+                    let Some(item) = items.get(seq as usize) else {
+                        self.send_mission_ack(mission_type, MavMissionResult::MAV_MISSION_INVALID_SEQUENCE).await?;
+                        return Err(MissionError::InvalidSequence(seq));
+                    };
+
+                    let message = if int {
+                        let mut item = item.with_int(self.system, self.id, seq);
+                        item.mission_type = mission_type;
+                        Message::MISSION_ITEM_INT(item)
+                    } else {
+                        let mut item = item.with(self.system, self.id, seq);
+                        item.mission_type = mission_type;
+                        Message::MISSION_ITEM(item)
+                    };
+
+                    self.link.send_message(message).await?;
+
+                    // Requests can come out of order, or again when an item
+                    // is lost, every one of them is answered.
+                    if std::mem::replace(&mut sent[seq as usize], true) {
+                        progress.retries += 1;
+                    } else {
+                        progress.sent += 1;
+                    }
+
+                    report(progress);
                 }
-                Message::MISSION_REQUEST_INT(req) => {
-                    let item = items[req.seq as usize].with_int(self.system, self.id, req.seq);
+                Ok(UploadResponse::Ack(MavMissionResult::MAV_MISSION_ACCEPTED)) => return Ok(()),
+                Ok(UploadResponse::Ack(result)) => return Err(MissionError::Rejected(result)),
+                Err(Error::Timeout) if timeouts < MAX_RETRY => {
+                    timeouts += 1;
+                    progress.retries += 1;
 
-                    let mission_item = Message::MISSION_ITEM_INT(item);
-                    self.link.send_message(mission_item).await?;
+                    // Once an item is requested, the component requests the
+                    // lost items by itself.
+                    if !requested {
+                        self.link.send_message(mission_count.clone()).await?;
+                    }
+
+                    report(progress);
                 }
-                Message::MISSION_ACK(ack) => {
-                    break ack.mavtype;
+                Err(Error::Timeout) => {
+                    // The component is still waiting for an item.
+                    if requested {
+                        self.send_mission_ack(mission_type, MavMissionResult::MAV_MISSION_OPERATION_CANCELLED).await?;
+                    }
+
+                    return Err(MissionError::Timeout);
                 }
-                _ => unreachable!(),
+                Err(err) => return Err(err.into()),
             }
-        };
+        }
+    }
+
+    async fn clear_items(&mut self, mission_type: MavMissionType) -> StdResult<(), MissionError> {
+        let addressed_to_us = addressed_to(&self.link);
+
+        let clear_all = Message::MISSION_CLEAR_ALL(MISSION_CLEAR_ALL_DATA {
+            target_system: self.system,
+            target_component: self.id,
+            mission_type,
+        });
+
+        let result = self.request(clear_all, |packet| match &packet.message {
+            Message::MISSION_ACK(ack) if addressed_to_us(packet) && ack.mission_type == mission_type => Some(ack.mavtype),
+            _ => None,
+        }).await?;
+
+        match result {
+            MavMissionResult::MAV_MISSION_ACCEPTED => Ok(()),
+            result => Err(MissionError::Rejected(result)),
+        }
+    }
+
+    async fn send_mission_ack(&mut self, mission_type: MavMissionType, result: MavMissionResult) -> Result<()> {
+        self.link.send_message(Message::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: self.system,
+            target_component: self.id,
+            mavtype: result,
+            mission_type,
+        })).await
+    }
+
+    /// Requests every parameter of the component, and returns them ordered by
+    /// their index.
+    ///
+    /// The parameters that are missing after the list is streamed are
+    /// requested one by one.
+    pub async fn request_parameters(&mut self) -> Result<Vec<Param>> {
+        let encoding = self.param_encoding;
+        let mut params = BTreeMap::new();
+        let mut count = None;
+        let mut retry = 0;
+
+        self.link.send_message(Message::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+            target_system: self.system,
+            target_component: self.id,
+        })).await?;
+
+        while count.is_none_or(|count| params.len() < count as usize) {
+            match self.wait_for(|packet| Param::from_packet(packet, encoding), ACK_TIMEOUT).await {
+                // Some autopilots report the parameters that are changed with
+                // an invalid index.
+                Ok(param) if param.index < param.count => {
+                    retry = 0;
+                    count = Some(param.count);
+                    params.insert(param.index, param);
+                }
+                Ok(_) => {}
+                Err(Error::Timeout) if retry < MAX_RETRY => {
+                    retry += 1;
+
+                    let Some(count) = count else {
+                        // Not even the first parameter arrived, start over.
+                        self.link.send_message(Message::PARAM_REQUEST_LIST(PARAM_REQUEST_LIST_DATA {
+                            target_system: self.system,
+                            target_component: self.id,
+                        })).await?;
+
+                        continue;
+                    };
+
+                    for index in (0..count).filter(|index| !params.contains_key(index)) {
+                        self.send_param_request_read(index as i16, [0; 16]).await?;
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(params.into_values().collect())
+    }
+
+    pub async fn read_parameter(&mut self, name: &str) -> Result<Param> {
+        let message = self.param_request_read(-1, param_id(name));
+        let encoding = self.param_encoding;
+
+        self.request(message, |packet| {
+            Param::from_packet(packet, encoding).filter(|param| param.name == name)
+        }).await
+    }
+
+    pub async fn read_parameter_by_index(&mut self, index: u16) -> Result<Param> {
+        let message = self.param_request_read(index as i16, [0; 16]);
+        let encoding = self.param_encoding;
+
+        self.request(message, |packet| {
+            Param::from_packet(packet, encoding).filter(|param| param.index == index)
+        }).await
+    }
+
+    /// Sets a parameter, and returns it as confirmed by the component.
+    ///
+    /// Returns `Error::Rejected` if the component confirms another value, as
+    /// autopilots keep the current value when the new one is invalid.
+    pub async fn set_parameter(&mut self, name: &str, value: ParamValue) -> Result<Param> {
+        let encoding = self.param_encoding;
+        let (param_value, param_type) = value.encode(encoding);
+
+        let message = Message::PARAM_SET(PARAM_SET_DATA {
+            param_value,
+            target_system: self.system,
+            target_component: self.id,
+            param_id: param_id(name),
+            param_type,
+        });
+
+        let param = self.request(message, |packet| {
+            Param::from_packet(packet, encoding).filter(|param| param.name == name)
+        }).await?;
 
-        Ok(mission_result)
+        // The component might report the value with another type.
+        if param.value.as_f64() == value.as_f64() {
+            Ok(param)
+        } else {
+            Err(Error::Rejected)
+        }
+    }
+
+    fn param_request_read(&self, param_index: i16, param_id: [u8; 16]) -> Message {
+        Message::PARAM_REQUEST_READ(PARAM_REQUEST_READ_DATA {
+            param_index,
+            target_system: self.system,
+            target_component: self.id,
+            param_id,
+        })
     }
 
+    async fn send_param_request_read(&mut self, param_index: i16, param_id: [u8; 16]) -> Result<()> {
+        let message = self.param_request_read(param_index, param_id);
+        self.link.send_message(message).await
+    }
+
+    /// Downloads the items of the given mission type from the component.
+    ///
+    /// ArduPilot reports the home position as the first item of the mission.
+    pub async fn download_mission(&mut self, mission_type: MavMissionType) -> StdResult<Vec<MissionItem>, MissionError> {
+        let items = self.download_items(mission_type).await?;
+        Ok(items.into_iter().map(MissionItem::from).collect())
+    }
+
+    pub async fn clear_mission(&mut self) -> StdResult<(), MissionError> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_MISSION).await
+    }
+
+    /// Makes the vehicle head to the given item of the mission.
+    ///
+    /// `MAV_CMD_DO_SET_MISSION_CURRENT` is tried first, and the deprecated
+    /// `MISSION_SET_CURRENT` is sent if the component does not support it.
+    pub async fn set_current_mission_item(&mut self, seq: u16) -> Result<()> {
+        let result = self.command_long(CommandLong {
+            command: MavCmd::MAV_CMD_DO_SET_MISSION_CURRENT,
+            param1: seq as f32,
+            ..Default::default()
+        }).await;
+
+        match result {
+            Ok(MavResult::MAV_RESULT_ACCEPTED) => return Ok(()),
+            Ok(MavResult::MAV_RESULT_UNSUPPORTED) | Err(Error::Timeout) => {}
+            Ok(_) => return Err(Error::Rejected),
+            Err(err) => return Err(err),
+        }
+
+        let set_current = Message::MISSION_SET_CURRENT(MISSION_SET_CURRENT_DATA {
+            seq,
+            target_system: self.system,
+            target_component: self.id,
+        });
+
+        // The change is confirmed by the next `MISSION_CURRENT`.
+        self.request(set_current, |packet| match &packet.message {
+            Message::MISSION_CURRENT(current) if current.seq == seq => Some(()),
+            _ => None,
+        }).await
+    }
+
+    /// Returns a stream of the events of the mission that the component is
+    /// flying, from the first time that it is polled.
+    pub fn mission_events(&self) -> impl Stream<Item = MissionEvent> {
+        let sender = self.sender();
+
+        // Subscribe on the first poll, a stream that is created but not read
+        // yet would hold up a link with back pressure.
+        stream::once(async move { sender.component() })
+            .flatten()
+            .scan(MissionTracker::default(), |tracker, packet| {
+                ready(Some(stream::iter(tracker.update(&packet.message))))
+            })
+            .flatten()
+    }
+
+    pub async fn upload_geofence(&mut self, fence: &Geofence) -> StdResult<(), MissionError> {
+        self.upload_items(MavMissionType::MAV_MISSION_TYPE_FENCE, &fence.items(), &AtomicBool::new(false), |_| {}).await
+    }
+
+    pub async fn download_geofence(&mut self) -> StdResult<Geofence, MissionError> {
+        let items = self.download_items(MavMissionType::MAV_MISSION_TYPE_FENCE).await?;
+        Geofence::from_items(&items)
+    }
+
+    pub async fn clear_geofence(&mut self) -> StdResult<(), MissionError> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_FENCE).await
+    }
+
+    pub async fn upload_rally_points<R>(&mut self, points: R) -> StdResult<(), MissionError>
+    where R: AsRef<[RallyPoint]>
+    {
+        self.upload_items(MavMissionType::MAV_MISSION_TYPE_RALLY, points.as_ref(), &AtomicBool::new(false), |_| {}).await
+    }
+
+    pub async fn download_rally_points(&mut self) -> StdResult<Vec<RallyPoint>, MissionError> {
+        let items = self.download_items(MavMissionType::MAV_MISSION_TYPE_RALLY).await?;
+        items.iter().map(RallyPoint::try_from).collect()
+    }
+
+    pub async fn clear_rally_points(&mut self) -> StdResult<(), MissionError> {
+        self.clear_items(MavMissionType::MAV_MISSION_TYPE_RALLY).await
+    }
+
+    // Runs the download side of the mission protocol.
+    async fn download_items(&mut self, mission_type: MavMissionType) -> StdResult<Vec<MISSION_ITEM_INT_DATA>, MissionError> {
+        let addressed_to_us = addressed_to(&self.link);
+
+        let request_list = Message::MISSION_REQUEST_LIST(MISSION_REQUEST_LIST_DATA {
+            target_system: self.system,
+            target_component: self.id,
+            mission_type,
+        });
+
+        let count = self.request(request_list, |packet| match &packet.message {
+            Message::MISSION_COUNT(count) if addressed_to_us(packet) && count.mission_type == mission_type => {
+                Some(Ok(count.count))
+            }
+            Message::MISSION_ACK(ack) if addressed_to_us(packet) && ack.mission_type == mission_type => {
+                Some(Err(MissionError::Rejected(ack.mavtype)))
+            }
+            _ => None,
+        }).await??;
+
+        let mut items = Vec::with_capacity(count as usize);
+
+        for seq in 0..count {
+            let request = Message::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+                seq,
+                target_system: self.system,
+                target_component: self.id,
+                mission_type,
+            });
+
+            let item = self.request(request, |packet| match &packet.message {
+                Message::MISSION_ITEM_INT(item) if addressed_to_us(packet) && item.seq == seq && item.mission_type == mission_type => {
+                    Some(Ok(item.clone()))
+                }
+                Message::MISSION_ACK(ack) if addressed_to_us(packet) && ack.mission_type == mission_type => {
+                    Some(Err(MissionError::Rejected(ack.mavtype)))
+                }
+                _ => None,
+            }).await??;
+
+            items.push(item);
+        }
+
+        // The component stops resending the last item once it is acked.
+        self.send_mission_ack(mission_type, MavMissionResult::MAV_MISSION_ACCEPTED).await?;
+
+        Ok(items)
+    }
+
+    #[cfg(feature = "ardupilotmega")]
     pub async fn set_mode(&mut self, mode: CopterMode) -> Result<MavResult> {
         self.command_long(CommandLong {
             command: MavCmd::MAV_CMD_DO_SET_MODE,
@@ -209,30 +670,111 @@ impl Component {
         }).await
     }
 
-    pub async fn wait_armable(&mut self) -> bool {
-        self.any(|packet| async move {
-            match &packet.message {
-                Message::SYS_STATUS(status) => {
-                    status
-                        .onboard_control_sensors_health
-                        .contains(MavSysStatusSensor::MAV_SYS_STATUS_PREARM_CHECK)
-                },
-                _ => false
-            }
-        }).await
+    /// Waits for a packet of the component that the predicate maps to a
+    /// value, and returns the value.
+    ///
+    /// Returns `Error::Timeout` if no such packet is received in time, and
+    /// `Error::Closed` if the link is closed.
+    pub async fn wait_for<T, F>(&mut self, mut predicate: F, timeout: Duration) -> Result<T>
+    where F: FnMut(&Packet) -> Option<T>
+    {
+        self._timeout(|packet| ready(predicate(&packet)), timeout).await
     }
 
-    pub async fn wait_armed(&mut self) -> bool {
-        self.any(|packet| async move {
-            match &packet.message {
-                Message::HEARTBEAT(heartbeat) => {
-                    heartbeat
-                        .base_mode
-                        .contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED)
-                },
-                _ => false
-            }
-        }) .await
+    pub async fn wait_armable(&mut self, timeout: Duration) -> Result<()> {
+        self.wait_for(|packet| match &packet.message {
+            Message::SYS_STATUS(status) => {
+                status
+                    .onboard_control_sensors_health
+                    .contains(MavSysStatusSensor::MAV_SYS_STATUS_PREARM_CHECK)
+                    .then_some(())
+            },
+            _ => None
+        }, timeout).await
+    }
+
+    pub async fn wait_armed(&mut self, timeout: Duration) -> Result<()> {
+        self.wait_for(|packet| is_armed(packet).filter(|armed| *armed).map(drop), timeout).await
+    }
+
+    pub async fn wait_disarmed(&mut self, timeout: Duration) -> Result<()> {
+        self.wait_for(|packet| is_armed(packet).filter(|armed| !armed).map(drop), timeout).await
+    }
+
+    #[cfg(feature = "ardupilotmega")]
+    pub async fn wait_mode(&mut self, mode: CopterMode, timeout: Duration) -> Result<()> {
+        self.wait_for(|packet| match &packet.message {
+            Message::HEARTBEAT(heartbeat) => (heartbeat.custom_mode == mode as u32).then_some(()),
+            _ => None
+        }, timeout).await
+    }
+
+    /// Waits until the altitude above home is within the tolerance of the
+    /// given altitude, and returns the altitude.
+    pub async fn wait_altitude(&mut self, altitude_m: f32, tolerance_m: f32, timeout: Duration) -> Result<f32> {
+        self.wait_for(|packet| match &packet.message {
+            Message::GLOBAL_POSITION_INT(position) => {
+                let relative_altitude_m = position.relative_alt as f32 / 1000.0;
+                ((relative_altitude_m - altitude_m).abs() <= tolerance_m).then_some(relative_altitude_m)
+            },
+            _ => None
+        }, timeout).await
+    }
+
+    /// Waits until the component reports that it is on the ground through
+    /// `EXTENDED_SYS_STATE`.
+    pub async fn wait_landed(&mut self, timeout: Duration) -> Result<()> {
+        self.wait_for(|packet| match &packet.message {
+            Message::EXTENDED_SYS_STATE(state) => {
+                (state.landed_state == MavLandedState::MAV_LANDED_STATE_ON_GROUND).then_some(())
+            },
+            _ => None
+        }, timeout).await
+    }
+
+    /// Returns a stream that yields the health of the component every time it
+    /// changes, starting from an unknown health when it is called. So the
+    /// first health is only yielded after a `SYS_STATUS` is received, see
+    /// [`HealthTracker`](crate::health::HealthTracker) for a health that is kept across calls.
+    pub fn health(&self) -> impl Stream<Item = Health> {
+        self.clone()
+            .scan(Health::default(), |health, packet| {
+                let changed = health.update(&packet.message);
+                ready(Some(changed.then_some(*health)))
+            })
+            .filter_map(ready)
+    }
+
+    /// Returns a stream of the battery status that is reported by the
+    /// component's `SYS_STATUS`.
+    pub fn battery(&self) -> impl Stream<Item = Battery> {
+        self.clone().filter_map(|packet| ready(match &packet.message {
+            Message::SYS_STATUS(status) => Some(status.into()),
+            _ => None,
+        }))
+    }
+
+    /// Sends the secret key and the current timestamp of the given signing
+    /// state to the component, so that it starts signing its packets.
+    ///
+    /// `SETUP_SIGNING` is not acknowledged, and it carries the key in plain
+    /// text, so this should only be done over a secure link.
+    pub async fn setup_signing(&mut self, signing: &Signing) -> Result<()> {
+        self.send_signing_key(*signing.key(), signing.timestamp()).await
+    }
+
+    /// Disables signing on the component by sending an empty key.
+    pub async fn disable_signing(&mut self) -> Result<()> {
+        self.send_signing_key(Default::default(), 0).await
+    }
+
+    async fn send_signing_key(&mut self, secret_key: SecretKey, initial_timestamp: u64) -> Result<()> {
+        self.link.send_message(Message::SETUP_SIGNING(SETUP_SIGNING_DATA {
+            initial_timestamp,
+            target_system: self.system,
+            target_component: self.id,
+            secret_key,
+        })).await
     }
 
     pub async fn manual_control(&mut self, mut data: MANUAL_CONTROL_DATA) -> Result<()> {
@@ -241,6 +783,22 @@ impl Component {
     }
 }
 
+/// A [`Component`] that does not receive, see [`LinkSender`].
+#[derive(Clone)]
+pub(crate) struct ComponentSender {
+    id: u8,
+    system: u8,
+    link: LinkSender,
+    param_encoding: ParamEncoding,
+}
+
+impl ComponentSender {
+    /// Returns a component that receives the packets that arrive from now on.
+    pub(crate) fn component(&self) -> Component {
+        Component { id: self.id, system: self.system, link: self.link.link(), param_encoding: self.param_encoding }
+    }
+}
+
 impl Stream for Component {
     type Item = Arc<Packet>;
 
@@ -262,25 +820,35 @@ impl Stream for Component {
     }
 }
 
-fn mission_filter(packet: Arc<Packet>) -> Ready<Option<Arc<Packet>>> {
-    use Message::{
-        MISSION_ACK as Ack,
-        MISSION_REQUEST as Request,
-        MISSION_REQUEST_INT as RequestInt,
-    };
+enum UploadResponse {
+    Request { seq: u16, int: bool },
+    Ack(MavMissionResult),
+}
 
+fn is_armed(packet: &Packet) -> Option<bool> {
     match &packet.message {
-        Request(_) | RequestInt(_) | Ack(_) => ready(Some(packet)),
-        _ => ready(None)
+        Message::HEARTBEAT(heartbeat) => {
+            Some(heartbeat.base_mode.contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED))
+        },
+        _ => None
     }
 }
 
-fn ack_filter(command: MavCmd) -> impl Fn(Arc<Packet>) -> Ready<Option<MavResult>> {
+// Ignore the responses that are addressed to someone else on the network.
+fn addressed_to(link: &Link) -> impl Fn(&Packet) -> bool {
+    let (system_id, component_id) = (link.system_id(), link.component_id());
+    move |packet| packet.is_addressed_to(system_id, component_id)
+}
+
+fn ack_filter(link: &Link, command: MavCmd) -> impl Fn(Arc<Packet>) -> Ready<Option<MavResult>> {
+    let addressed_to_us = addressed_to(link);
+
     move |packet| {
-        if let Message::COMMAND_ACK(ack) = &packet.message {
-            ready(command.eq(&ack.command).then_some(ack.result))
-        } else {
-            ready(None)
+        match &packet.message {
+            Message::COMMAND_ACK(ack) if addressed_to_us(&packet) => {
+                ready(command.eq(&ack.command).then_some(ack.result))
+            }
+            _ => ready(None)
         }
     }
 }
@@ -288,6 +856,7 @@ fn ack_filter(command: MavCmd) -> impl Fn(Arc<Packet>) -> Ready<Option<MavResult
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{health::HealthTracker, wire::Frame};
 
     // Test whether the component streams *only* the packets that carry its 
     // system and component ids.
@@ -309,11 +878,11 @@ mod tests {
         let component_packets = messages
             .clone()
             .into_iter()
-            .map(|message| Packet { header, message } );
+            .map(|message| Packet { header, message, version: Version::V2 } );
 
         let unrecognized_component_packets = messages
             .into_iter()
-            .map(|message| Packet { header: unrecognized, message } );
+            .map(|message| Packet { header: unrecognized, message, version: Version::V2 } );
 
         let component_packets_continued = component_packets.clone();
 
@@ -333,4 +902,569 @@ mod tests {
 
         assert_eq!(number_of_messages_to_be_received, count);
     }
+
+    // Test whether the component ignores the acks that are addressed to
+    // another GCS on the same network.
+    #[tokio::test]
+    async fn component_ignores_acks_for_others() {
+        let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+        let ack = |target_system, result| Packet {
+            header,
+            message: Message::COMMAND_ACK(COMMAND_ACK_DATA {
+                command: MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+                result,
+                target_system,
+                target_component: 190,
+                ..Default::default()
+            }),
+            version: Version::V2,
+        };
+
+        let packets = [
+            ack(254, MavResult::MAV_RESULT_DENIED),
+            ack(255, MavResult::MAV_RESULT_ACCEPTED),
+        ];
+
+        let stream = futures::stream::iter(packets);
+        let sink = futures::sink::drain();
+
+        let (link, connection) = Link::new(sink, stream, 255, 190);
+        let mut component = Component::new(1, 1, link);
+
+        tokio::spawn(connection);
+
+        assert_eq!(component.arm(true).await.unwrap(), MavResult::MAV_RESULT_ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn component_waits_for_health() {
+        use MavSysStatusSensor as Sensor;
+
+        let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+        let sensors = Sensor::MAV_SYS_STATUS_SENSOR_3D_GYRO
+            | Sensor::MAV_SYS_STATUS_SENSOR_3D_ACCEL
+            | Sensor::MAV_SYS_STATUS_SENSOR_3D_MAG
+            | Sensor::MAV_SYS_STATUS_SENSOR_GPS
+            | Sensor::MAV_SYS_STATUS_SENSOR_VISION_POSITION;
+
+        let status = |health| Message::SYS_STATUS(SYS_STATUS_DATA {
+            onboard_control_sensors_present: sensors,
+            onboard_control_sensors_enabled: sensors,
+            onboard_control_sensors_health: health,
+            voltage_battery: 16_800,
+            battery_remaining: 100,
+            ..Default::default()
+        });
+
+        let messages = [
+            status(sensors),
+            Message::HOME_POSITION(Default::default()),
+            status(sensors | Sensor::MAV_SYS_STATUS_PREARM_CHECK),
+        ];
+
+        let packets = messages.map(|message| Packet { header, message, version: Version::V2 });
+        let stream = futures::stream::iter(packets).chain(futures::stream::pending());
+        let (link, connection) = Link::new(futures::sink::drain(), stream, 255, 190);
+        let component = Component::new(1, 1, link);
+
+        let health_changes = Box::pin(component.health());
+        let battery = component.battery();
+        let (tracker, tracking) = HealthTracker::new(component.clone());
+
+        tokio::spawn(connection);
+        tokio::spawn(tracking);
+
+        let health = tracker.wait_health_ok(Duration::from_secs(1)).await.unwrap();
+        assert!(health.is_all_ok());
+
+        // Every change is streamed.
+        let changes: Vec<_> = health_changes.take(3).collect().await;
+        assert_eq!(changes.last(), Some(&health));
+        assert!(!changes[0].is_home_position_ok && !changes[1].is_armable);
+
+        let battery = Box::pin(battery).next().await.unwrap();
+        assert_eq!(battery.voltage_v, 16.8);
+        assert_eq!(battery.remaining_percent, Some(100.0));
+    }
+
+    #[tokio::test]
+    async fn component_waits_for_states() {
+        let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+        let heartbeat = |base_mode| Message::HEARTBEAT(HEARTBEAT_DATA { base_mode, ..Default::default() });
+        let altitude = |relative_alt| Message::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+            relative_alt,
+            ..Default::default()
+        });
+
+        let messages = [
+            heartbeat(MavModeFlag::empty()),
+            altitude(2_000),
+            heartbeat(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED),
+            altitude(9_800),
+            heartbeat(MavModeFlag::empty()),
+        ];
+
+        let packets = messages.map(|message| Packet { header, message, version: Version::V2 });
+        let (link, connection) = Link::new(futures::sink::drain(), futures::stream::iter(packets), 255, 190);
+        let mut component = Component::new(1, 1, link);
+        let second = Duration::from_secs(1);
+
+        tokio::spawn(connection);
+
+        component.wait_armed(second).await.unwrap();
+        assert_eq!(component.wait_altitude(10.0, 0.5, second).await.unwrap(), 9.8);
+        component.wait_disarmed(second).await.unwrap();
+
+        // The link is closed after the last packet.
+        assert!(matches!(component.wait_landed(second).await, Err(Error::Closed)));
+
+        // A link that stays silent times out.
+        let (link, connection) = Link::new(futures::sink::drain(), futures::stream::pending::<Packet>(), 255, 190);
+        let mut component = Component::new(1, 1, link);
+
+        tokio::spawn(connection);
+
+        let result = component.wait_for(|_| Some(()), Duration::from_millis(10)).await;
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn component_detects_the_param_encoding() {
+        let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+        let messages = [
+            Message::HEARTBEAT(HEARTBEAT_DATA { autopilot: MavAutopilot::MAV_AUTOPILOT_PX4, ..Default::default() }),
+            // Capabilities that tell nothing about the encoding are skipped.
+            Message::AUTOPILOT_VERSION(Default::default()),
+            Message::AUTOPILOT_VERSION(AUTOPILOT_VERSION_DATA {
+                capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_C_CAST,
+                ..Default::default()
+            }),
+        ];
+
+        let packets = messages.map(|message| Packet { header, message, version: Version::V2 });
+        let (link, connection) = Link::new(futures::sink::drain(), futures::stream::iter(packets), 255, 190);
+        let mut component = Component::new(1, 1, link);
+        let second = Duration::from_secs(1);
+
+        tokio::spawn(connection);
+
+        assert_eq!(component.param_encoding(), ParamEncoding::CCast);
+        assert_eq!(component.detect_param_encoding(second).await.unwrap(), ParamEncoding::Bytewise);
+        assert_eq!(component.detect_param_encoding(second).await.unwrap(), ParamEncoding::CCast);
+
+        // The encoding is kept when nothing tells it.
+        assert!(component.detect_param_encoding(second).await.is_err());
+        assert_eq!(component.param_encoding(), ParamEncoding::CCast);
+    }
+
+    // The autopilot end of a link, which is component 1 of system 1.
+    struct FakeAutopilot {
+        requests: flume::Receiver<Frame>,
+        responses: flume::Sender<Packet>,
+    }
+
+    impl FakeAutopilot {
+        // Returns a link to the autopilot, whose connection is spawned.
+        fn new() -> (Link, FakeAutopilot) {
+            let (requests, requests_receiver) = flume::unbounded();
+            let (responses, responses_receiver) = flume::unbounded();
+            let (link, connection) = Link::new(requests.into_sink(), responses_receiver.into_stream(), 255, 190);
+
+            tokio::spawn(connection);
+            (link, FakeAutopilot { requests: requests_receiver, responses })
+        }
+
+        // Returns the next packet that is sent to the autopilot, or `None`
+        // when the link is closed.
+        async fn recv(&self) -> Option<Packet> {
+            while let Ok(frame) = self.requests.recv_async().await {
+                if let Frame::Packet(packet) = frame {
+                    return Some(packet);
+                }
+            }
+
+            None
+        }
+
+        fn send(&self, message: Message) {
+            let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+            self.responses.send(Packet { header, message, version: Version::V2 }).unwrap();
+        }
+    }
+
+    // Test whether a mission is downloaded when the autopilot loses one of the
+    // item requests.
+    #[tokio::test]
+    async fn component_downloads_missions() {
+        let (link, autopilot) = FakeAutopilot::new();
+        let mut component = Component::new(1, 1, link);
+
+        let mission = [
+            MissionItem::Takeoff(0.0, 0.0, 10.0),
+            MissionItem::Waypoint(38.5, 27.25, 20.0),
+            MissionItem::ReturnToLaunch,
+        ];
+
+        let autopilot = async move {
+            let mut lost = false;
+
+            while let Some(request) = autopilot.recv().await {
+                let message = match request.message {
+                    Message::MISSION_REQUEST_LIST(list) => Message::MISSION_COUNT(MISSION_COUNT_DATA {
+                        count: mission.len() as u16,
+                        target_system: 255,
+                        target_component: 190,
+                        mission_type: list.mission_type,
+                    }),
+                    Message::MISSION_REQUEST_INT(req) if req.seq == 1 && !lost => {
+                        lost = true;
+                        continue;
+                    }
+                    Message::MISSION_REQUEST_INT(req) => {
+                        Message::MISSION_ITEM_INT(mission[req.seq as usize].with_int(255, 190, req.seq))
+                    }
+                    Message::MISSION_ACK(ack) => return ack.mavtype,
+                    _ => continue,
+                };
+
+                autopilot.send(message);
+            }
+
+            MavMissionResult::MAV_MISSION_ERROR
+        };
+
+        let autopilot = tokio::spawn(autopilot);
+
+        let items = component.download_mission(MavMissionType::MAV_MISSION_TYPE_MISSION).await.unwrap();
+
+        assert_eq!(items, [
+            MissionItem::Takeoff(0.0, 0.0, 10.0),
+            MissionItem::Waypoint(38.5, 27.25, 20.0),
+            MissionItem::ReturnToLaunch,
+        ]);
+
+        assert_eq!(autopilot.await.unwrap(), MavMissionResult::MAV_MISSION_ACCEPTED);
+    }
+
+    // An autopilot that ignores the first MISSION_COUNT, requests the given
+    // sequence of items, and then ends with the result, or stops answering.
+    async fn mission_autopilot(
+        autopilot: FakeAutopilot,
+        sequence: Vec<u16>,
+        result: Option<MavMissionResult>,
+    ) -> Vec<Packet> {
+        let mut received = Vec::new();
+        let mut sequence = sequence.into_iter();
+
+        while let Some(request) = autopilot.recv().await {
+            let is_first = received.is_empty();
+            let is_ack = matches!(request.message, Message::MISSION_ACK(_));
+            received.push(request);
+
+            if is_first || is_ack {
+                continue;
+            }
+
+            let message = match sequence.next() {
+                Some(seq) => Message::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+                    seq,
+                    target_system: 255,
+                    target_component: 190,
+                    mission_type: MavMissionType::MAV_MISSION_TYPE_MISSION,
+                }),
+                None => match result {
+                    Some(mavtype) => Message::MISSION_ACK(MISSION_ACK_DATA {
+                        target_system: 255,
+                        target_component: 190,
+                        mavtype,
+                        mission_type: MavMissionType::MAV_MISSION_TYPE_MISSION,
+                    }),
+                    None => continue,
+                },
+            };
+
+            autopilot.send(message);
+        }
+
+        received
+    }
+
+    async fn upload(sequence: Vec<u16>, result: Option<MavMissionResult>) -> (StdResult<(), MissionError>, Vec<UploadProgress>, Vec<Packet>) {
+        let (link, autopilot) = FakeAutopilot::new();
+        let component = Component::new(1, 1, link);
+        let autopilot = tokio::spawn(mission_autopilot(autopilot, sequence, result));
+
+        let mission = [
+            MissionItem::Takeoff(0.0, 0.0, 10.0),
+            MissionItem::Waypoint(38.5, 27.25, 20.0),
+            MissionItem::ReturnToLaunch,
+        ];
+
+        let mut upload = component.start_mission_upload(mission);
+        let progress: Vec<_> = upload.by_ref().collect().await;
+        let result = upload.finish().await;
+
+        drop(component);
+        (result, progress, autopilot.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn component_uploads_missions() {
+        use MavMissionResult::*;
+
+        // The count is sent again, and the duplicate and out of order requests
+        // are answered.
+        let (result, progress, received) = upload(vec![0, 2, 2, 1], Some(MAV_MISSION_ACCEPTED)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(progress.first(), Some(&UploadProgress { sent: 0, total: 3, retries: 1 }));
+        assert_eq!(progress.last(), Some(&UploadProgress { sent: 3, total: 3, retries: 2 }));
+
+        let seqs: Vec<_> = received.iter().filter_map(|packet| match &packet.message {
+            Message::MISSION_ITEM_INT(item) => Some(item.seq),
+            _ => None,
+        }).collect();
+
+        assert!(matches!(received[1].message, Message::MISSION_COUNT(MISSION_COUNT_DATA { count: 3, .. })));
+        assert_eq!(seqs, [0, 2, 2, 1]);
+
+        // The component is told about the invalid request.
+        let (result, _, received) = upload(vec![0, 7], Some(MAV_MISSION_ACCEPTED)).await;
+
+        assert!(matches!(result, Err(MissionError::InvalidSequence(7))));
+        assert!(matches!(
+            received.last().unwrap().message,
+            Message::MISSION_ACK(MISSION_ACK_DATA { mavtype: MAV_MISSION_INVALID_SEQUENCE, .. })
+        ));
+
+        let (result, _, _) = upload(vec![0, 1, 2], Some(MAV_MISSION_NO_SPACE)).await;
+        assert!(matches!(result, Err(MissionError::Rejected(MAV_MISSION_NO_SPACE))));
+
+        // The component that stops answering is told that the upload ended.
+        let (result, _, received) = upload(vec![0], None).await;
+
+        assert!(matches!(result, Err(MissionError::Timeout)));
+        assert!(matches!(
+            received.last().unwrap().message,
+            Message::MISSION_ACK(MISSION_ACK_DATA { mavtype: MAV_MISSION_OPERATION_CANCELLED, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn component_ignores_stale_mission_acks() {
+        let (link, autopilot) = FakeAutopilot::new();
+        let mut observer = link.clone();
+        let mut component = Component::new(1, 1, link);
+
+        // The late ack of an earlier upload.
+        autopilot.send(Message::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: 255,
+            target_component: 190,
+            mavtype: MavMissionResult::MAV_MISSION_ERROR,
+            mission_type: MavMissionType::MAV_MISSION_TYPE_MISSION,
+        }));
+
+        observer.recv().await.unwrap();
+
+        let sequence = vec![0, 1];
+        let result = Some(MavMissionResult::MAV_MISSION_ACCEPTED);
+        let autopilot = tokio::spawn(mission_autopilot(autopilot, sequence, result));
+
+        let mission = [MissionItem::Takeoff(0.0, 0.0, 10.0), MissionItem::ReturnToLaunch];
+        assert!(component.upload_mission(mission).await.is_ok());
+
+        drop((component, observer));
+        assert_eq!(autopilot.await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn component_cancels_mission_uploads() {
+        let (link, autopilot) = FakeAutopilot::new();
+        let component = Component::new(1, 1, link);
+
+        let sequence = vec![0, 1, 2];
+        let result = Some(MavMissionResult::MAV_MISSION_ACCEPTED);
+        let autopilot = tokio::spawn(mission_autopilot(autopilot, sequence, result));
+
+        let mission = [MissionItem::Takeoff(0.0, 0.0, 10.0), MissionItem::ReturnToLaunch, MissionItem::ReturnToLaunch];
+        let mut upload = component.start_mission_upload(mission);
+
+        // The lost count is retried, and the first item is sent.
+        assert_eq!(upload.next().await, Some(UploadProgress { sent: 0, total: 3, retries: 1 }));
+        assert_eq!(upload.next().await, Some(UploadProgress { sent: 1, total: 3, retries: 1 }));
+
+        upload.cancel();
+        drop(component);
+
+        let received = autopilot.await.unwrap();
+        assert!(matches!(
+            received.last().unwrap().message,
+            Message::MISSION_ACK(MISSION_ACK_DATA { mavtype: MavMissionResult::MAV_MISSION_OPERATION_CANCELLED, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn mission_upload_does_not_hold_up_back_pressure() {
+        use crate::link::Overflow;
+
+        let packets = futures::stream::iter(0..5).map(|sequence| Packet {
+            header: Header { system_id: 1, component_id: 1, sequence },
+            ..Default::default()
+        });
+
+        let builder = Link::builder().incoming_capacity(2).overflow(Overflow::BackPressure);
+        let (mut link, connection) = builder.build(futures::sink::drain(), packets, 255, 190);
+        tokio::spawn(connection);
+
+        // An upload that is not polled yet does not receive.
+        let component = Component::new(1, 1, link.clone());
+        let _upload = component.start_mission_upload([MissionItem::ReturnToLaunch]);
+        drop(component);
+
+        for sequence in 0..5 {
+            assert_eq!(link.recv().await.unwrap().header.sequence, sequence);
+        }
+    }
+
+    #[tokio::test]
+    async fn mission_upload_is_only_cancelled_once_started() {
+        let (link, autopilot) = FakeAutopilot::new();
+        let component = Component::new(1, 1, link);
+
+        // The component never heard of the upload.
+        component.start_mission_upload([MissionItem::ReturnToLaunch]).cancel();
+        drop(component);
+
+        assert!(autopilot.recv().await.is_none());
+    }
+
+    // An autopilot that stores the items of every mission type.
+    async fn mission_server(autopilot: FakeAutopilot) {
+        use std::collections::HashMap;
+
+        let ack = |mission_type| Message::MISSION_ACK(MISSION_ACK_DATA {
+            target_system: 255,
+            target_component: 190,
+            mavtype: MavMissionResult::MAV_MISSION_ACCEPTED,
+            mission_type,
+        });
+
+        let request = |seq, mission_type| Message::MISSION_REQUEST_INT(MISSION_REQUEST_INT_DATA {
+            seq,
+            target_system: 255,
+            target_component: 190,
+            mission_type,
+        });
+
+        let mut missions: HashMap<u8, Vec<MISSION_ITEM_INT_DATA>> = HashMap::new();
+        let mut upload = None;
+
+        while let Some(packet) = autopilot.recv().await {
+            let message = match packet.message {
+                Message::MISSION_COUNT(count) => {
+                    upload = Some((count.count, Vec::new()));
+                    request(0, count.mission_type)
+                }
+                Message::MISSION_ITEM_INT(item) => {
+                    let mission_type = item.mission_type;
+                    let (count, items) = upload.as_mut().unwrap();
+                    items.push(item);
+
+                    if items.len() < *count as usize {
+                        request(items.len() as u16, mission_type)
+                    } else {
+                        missions.insert(mission_type as u8, upload.take().unwrap().1);
+                        ack(mission_type)
+                    }
+                }
+                Message::MISSION_REQUEST_LIST(list) => Message::MISSION_COUNT(MISSION_COUNT_DATA {
+                    count: missions.get(&(list.mission_type as u8)).map_or(0, Vec::len) as u16,
+                    target_system: 255,
+                    target_component: 190,
+                    mission_type: list.mission_type,
+                }),
+                Message::MISSION_REQUEST_INT(req) => {
+                    let mut item = missions[&(req.mission_type as u8)][req.seq as usize].clone();
+                    item.target_system = 255;
+                    item.target_component = 190;
+                    Message::MISSION_ITEM_INT(item)
+                }
+                Message::MISSION_CLEAR_ALL(clear) => {
+                    missions.remove(&(clear.mission_type as u8));
+                    ack(clear.mission_type)
+                }
+                _ => continue,
+            };
+
+            autopilot.send(message);
+        }
+    }
+
+    #[tokio::test]
+    async fn component_transfers_geofences_and_rally_points() {
+        use crate::mission::{Coordinate, FenceZone};
+
+        let (link, autopilot) = FakeAutopilot::new();
+        let mut component = Component::new(1, 1, link);
+
+        tokio::spawn(mission_server(autopilot));
+
+        let vertices = vec![Coordinate::new(38.1, 27.1), Coordinate::new(38.2, 27.1), Coordinate::new(38.2, 27.2)];
+        let fence = Geofence {
+            zones: vec![FenceZone::Polygon { vertices, inclusion: true }],
+            return_point: None,
+        };
+
+        let rally = [RallyPoint { coordinate: Coordinate::new(38.15, 27.15), altitude_m: 20.0 }];
+
+        component.upload_geofence(&fence).await.unwrap();
+        component.upload_rally_points(rally).await.unwrap();
+
+        // The mission types are kept apart.
+        assert_eq!(component.download_geofence().await.unwrap(), fence);
+        assert_eq!(component.download_rally_points().await.unwrap(), rally);
+        assert!(component.download_mission(MavMissionType::MAV_MISSION_TYPE_MISSION).await.unwrap().is_empty());
+
+        component.clear_geofence().await.unwrap();
+        assert_eq!(component.download_geofence().await.unwrap(), Geofence::default());
+        assert_eq!(component.download_rally_points().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn component_sets_the_current_mission_item() {
+        let (link, autopilot) = FakeAutopilot::new();
+        let mut component = Component::new(1, 1, link);
+        let mut events = Box::pin(component.mission_events());
+
+        // An autopilot that only supports `MISSION_SET_CURRENT`.
+        let autopilot = async move {
+            while let Some(request) = autopilot.recv().await {
+                let message = match request.message {
+                    Message::COMMAND_LONG(command) => Message::COMMAND_ACK(COMMAND_ACK_DATA {
+                        command: command.command,
+                        result: MavResult::MAV_RESULT_UNSUPPORTED,
+                        target_system: 255,
+                        target_component: 190,
+                        ..Default::default()
+                    }),
+                    Message::MISSION_SET_CURRENT(set) => Message::MISSION_CURRENT(MISSION_CURRENT_DATA {
+                        seq: set.seq,
+                        total: 3,
+                        ..Default::default()
+                    }),
+                    _ => continue,
+                };
+
+                autopilot.send(message);
+            }
+        };
+
+        tokio::spawn(autopilot);
+
+        // The events are reported from the first poll.
+        assert!(futures::poll!(events.next()).is_pending());
+
+        component.set_current_mission_item(2).await.unwrap();
+        assert_eq!(events.next().await, Some(MissionEvent::Current { seq: 2, total: Some(3) }));
+    }
 }
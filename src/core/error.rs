@@ -10,13 +10,32 @@ pub enum Error {
     Send,
     Timeout,
     Closed,
+    /// The link fell behind, and missed this many packets.
+    Lagged(u64),
+    /// The component refused the request, such as a parameter value that is
+    /// out of range.
+    Rejected,
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("TODO: impl Display for Error")
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Send => f.write_str("could not send the message"),
+            Error::Timeout => f.write_str("timed out"),
+            Error::Closed => f.write_str("the channel is closed"),
+            Error::Lagged(missed) => write!(f, "the link fell behind and missed {missed} packets"),
+            Error::Rejected => f.write_str("the component rejected the request"),
+        }
     }
 }
 
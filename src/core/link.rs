@@ -1,9 +1,11 @@
 use async_broadcast as broadcast;
 use crate::{
-    dialect::{Header, Message},
+    dialect::{Header, Message, MessageExt, Version},
     error::{Error, Result},
-    wire::Packet,
+    stats::LinkStats,
+    wire::{DecodeEvent, Frame, Packet, RawPacket},
 };
+use futures_time::{stream::interval, time::Duration as FuturesTimeDuration};
 use futures_util::{
     pin_mut,
     future::{join, Future, FutureExt},
@@ -11,66 +13,418 @@ use futures_util::{
 };
 use std::{
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
-#[derive(Clone)]
-pub struct Link {
-    sender: flume::Sender<Message>,
-    pub(crate) subscriber: broadcast::Receiver<Arc<Packet>>,
+/// A connection that sends and receives packets of the dialect `M`.
+///
+/// Raw packets of messages that are not in the dialect are sent with
+/// [`send_raw`](Link::send_raw), and received with
+/// [`recv_incoming`](Link::recv_incoming), every other way of receiving
+/// skips them.
+pub struct Link<M = Message> {
+    sender: flume::Sender<Outgoing<M>>,
+    subscriber: broadcast::Receiver<Incoming<M>>,
+    system_id: u8,
+    component_id: u8,
+    stats: Arc<Mutex<LinkStats>>,
+    overflow: Overflow,
+    missed: u64,
+}
+
+impl<M> Clone for Link<M> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            subscriber: self.subscriber.clone(),
+            system_id: self.system_id,
+            component_id: self.component_id,
+            stats: self.stats.clone(),
+            overflow: self.overflow,
+            missed: 0,
+        }
+    }
 }
 
 impl Link {
+    /// Returns a builder for links with custom channel capacities and overflow
+    /// policies, which builds links of any dialect.
+    pub fn builder() -> LinkBuilder {
+        LinkBuilder::new()
+    }
+}
+
+impl<M: MessageExt> Link<M> {
     /// Constructs a new `Link` for the given `Sink` and `Stream` interfaces.
     ///
+    /// The `Stream` yields either packets, or decode events whose invalid
+    /// frames are counted in the [`stats`](Link::stats) of the link.
+    ///
     /// This function returns a `Link` and a future. The future must be spawned
     /// in order to forward outgoing messages, and broadcast incoming messages
     /// through the `Link`.
     ///
     /// The returned future will not resolve until one of the following is true:
     ///
-    /// * A broadcast is attempted when all `Link` instances are dropped.
+    /// * A broadcast is attempted when all `Link` and [`LinkSender`] instances
+    ///   are dropped.
     /// * The given `Sink` encounters an error *and* the `Stream` is exhausted.
     ///
     /// Notice that if the Sink encounters an error, but the `Stream` is not
     /// exhausted, the future will run until the `Stream` is exhausted.
+    ///
+    /// A `LinkSender` keeps the connection running on its own, so that it can
+    /// still send, and the packets that arrive while no `Link` is left are
+    /// dropped.
     pub fn new<T, U>(
         outgoing: T,
         incoming: U,
         system_id: u8,
         component_id: u8,
-    ) -> (Link, impl Future<Output = ()>)
+    ) -> (Link<M>, impl Future<Output = ()>)
+    where
+        T: Sink<Frame<M>>,
+        U: Stream,
+        U::Item: Into<DecodeEvent<M>>,
+    {
+        LinkBuilder::new().build(outgoing, incoming, system_id, component_id)
+    }
+
+    /// Receives the next incoming packet.
+    ///
+    /// With [`Overflow::Notify`], this returns [`Error::Lagged`] once for every
+    /// time that the link fell behind, with the number of packets it missed.
+    pub async fn recv(&mut self) -> Result<Arc<Packet<M>>> {
+        loop {
+            if let Incoming::Packet(packet) = self.recv_incoming().await? {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Receives the next incoming packet or raw packet, see [`Link::recv`].
+    pub async fn recv_incoming(&mut self) -> Result<Incoming<M>> {
+        loop {
+            match self.subscriber.recv_direct().await {
+                Ok(incoming) => return Ok(incoming),
+                Err(broadcast::RecvError::Overflowed(missed)) => {
+                    self.missed += missed;
+
+                    if self.overflow == Overflow::Notify {
+                        return Err(Error::Lagged(missed));
+                    }
+                }
+                Err(broadcast::RecvError::Closed) => return Err(Error::Closed),
+            }
+        }
+    }
+
+    /// Receives the next incoming packet without waiting.
+    pub fn try_recv(&mut self) -> std::result::Result<Arc<Packet<M>>, broadcast::TryRecvError> {
+        loop {
+            match self.subscriber.try_recv() {
+                Ok(Incoming::Packet(packet)) => return Ok(packet),
+                Ok(Incoming::Raw(_)) => {}
+                Err(broadcast::TryRecvError::Overflowed(missed)) => self.missed += missed,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Returns the number of incoming packets that this link, not counting
+    /// its clones, missed because it fell behind.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    pub async fn send_message(&self, message: M) -> Result<()> {
+        self.sender.send_async(Outgoing::Message(message)).await.or(Err(Error::Send))
+    }
+
+    /// Sends a message without waiting, which fails if the outgoing channel
+    /// is full.
+    pub fn try_send_message(&self, message: M) -> Result<()> {
+        self.sender.try_send(Outgoing::Message(message)).or(Err(Error::Send))
+    }
+
+    /// Sends a raw packet of a message that is not in the dialect.
+    ///
+    /// The header of the packet is replaced by the header of the link, so the
+    /// CRC extra of the packet must be set for its checksum to be
+    /// recalculated.
+    pub async fn send_raw(&self, packet: RawPacket) -> Result<()> {
+        self.sender.send_async(Outgoing::Raw(packet)).await.or(Err(Error::Send))
+    }
+
+    /// The system id of the outgoing packets.
+    pub fn system_id(&self) -> u8 {
+        self.system_id
+    }
+
+    /// The component id of the outgoing packets.
+    pub fn component_id(&self) -> u8 {
+        self.component_id
+    }
+
+    /// Returns a snapshot of the statistics of the link, which are shared by
+    /// all of its clones.
+    pub fn stats(&self) -> LinkStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Returns a stream that yields a snapshot of the statistics of the link
+    /// every `period`.
+    pub fn stats_updates(&self, period: Duration) -> impl Stream<Item = LinkStats> {
+        let stats = self.stats.clone();
+        interval(FuturesTimeDuration::from(period)).map(move |_| stats.lock().unwrap().clone())
+    }
+
+    /// Returns whether an incoming packet is addressed to this link, packets
+    /// without a target are addressed to everyone.
+    pub fn accepts(&self, packet: &Packet<M>) -> bool {
+        packet.is_addressed_to(self.system_id, self.component_id)
+    }
+
+    /// Returns a handle that only sends through the link, which is what
+    /// anything that does not receive should keep.
+    pub fn sender(&self) -> LinkSender<M> {
+        LinkSender {
+            sender: self.sender.clone(),
+            subscriber: self.subscriber.clone().deactivate(),
+            system_id: self.system_id,
+            component_id: self.component_id,
+            stats: self.stats.clone(),
+            overflow: self.overflow,
+        }
+    }
+}
+
+/// A handle that sends through a [`Link`] without receiving from it, see
+/// [`Link::sender`].
+///
+/// Unlike a `Link` that is not read from, a `LinkSender` never holds up a
+/// connection with [`Overflow::BackPressure`], but it keeps the connection
+/// running after every `Link` is dropped, see [`Link::new`].
+pub struct LinkSender<M = Message> {
+    sender: flume::Sender<Outgoing<M>>,
+    subscriber: broadcast::InactiveReceiver<Incoming<M>>,
+    system_id: u8,
+    component_id: u8,
+    stats: Arc<Mutex<LinkStats>>,
+    overflow: Overflow,
+}
+
+impl<M> Clone for LinkSender<M> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            subscriber: self.subscriber.clone(),
+            system_id: self.system_id,
+            component_id: self.component_id,
+            stats: self.stats.clone(),
+            overflow: self.overflow,
+        }
+    }
+}
+
+impl<M> LinkSender<M> {
+    /// Returns a link that receives the packets that arrive from now on.
+    pub fn link(&self) -> Link<M> {
+        Link {
+            sender: self.sender.clone(),
+            subscriber: self.subscriber.activate_cloned(),
+            system_id: self.system_id,
+            component_id: self.component_id,
+            stats: self.stats.clone(),
+            overflow: self.overflow,
+            missed: 0,
+        }
+    }
+
+    pub async fn send_message(&self, message: M) -> Result<()> {
+        self.sender.send_async(Outgoing::Message(message)).await.or(Err(Error::Send))
+    }
+
+    /// See [`Link::try_send_message`].
+    pub fn try_send_message(&self, message: M) -> Result<()> {
+        self.sender.try_send(Outgoing::Message(message)).or(Err(Error::Send))
+    }
+
+    /// See [`Link::send_raw`].
+    pub async fn send_raw(&self, packet: RawPacket) -> Result<()> {
+        self.sender.send_async(Outgoing::Raw(packet)).await.or(Err(Error::Send))
+    }
+
+    pub fn system_id(&self) -> u8 {
+        self.system_id
+    }
+
+    pub fn component_id(&self) -> u8 {
+        self.component_id
+    }
+
+    pub fn stats(&self) -> LinkStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// A packet received through a [`Link`], see [`Link::recv_incoming`].
+#[derive(Debug)]
+pub enum Incoming<M = Message> {
+    Packet(Arc<Packet<M>>),
+    /// A raw packet of a message that is not in the dialect.
+    Raw(Arc<RawPacket>),
+}
+
+impl<M> Clone for Incoming<M> {
+    fn clone(&self) -> Self {
+        match self {
+            Incoming::Packet(packet) => Incoming::Packet(packet.clone()),
+            Incoming::Raw(packet) => Incoming::Raw(packet.clone()),
+        }
+    }
+}
+
+/// A message or a raw packet that waits for its header.
+enum Outgoing<M> {
+    Message(M),
+    Raw(RawPacket),
+}
+
+/// What happens to incoming packets when a subscriber falls behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Drop the oldest packets for the subscribers that fell behind.
+    #[default]
+    DropOldest,
+
+    /// Wait for the slowest subscriber before receiving more packets, which
+    /// slows down the connection. A link that is never read from stalls the
+    /// connection, so every clone must be read or dropped, and anything that
+    /// only sends should keep a [`LinkSender`].
+    BackPressure,
+
+    /// Drop the oldest packets like [`Overflow::DropOldest`], but also report
+    /// the number of missed packets through [`Link::recv`].
+    Notify,
+}
+
+/// Builds [`Link`]s with custom channel capacities and overflow policies.
+///
+/// The defaults are the same as [`Link::new`]: 64 outgoing messages, 64
+/// incoming packets, and [`Overflow::DropOldest`].
+#[derive(Debug, Clone, Copy)]
+pub struct LinkBuilder {
+    outgoing_capacity: usize,
+    incoming_capacity: usize,
+    overflow: Overflow,
+}
+
+impl Default for LinkBuilder {
+    fn default() -> Self {
+        Self {
+            outgoing_capacity: 64,
+            incoming_capacity: 64,
+            overflow: Default::default(),
+        }
+    }
+}
+
+impl LinkBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of outgoing messages that can be queued before sending
+    /// waits.
+    pub fn outgoing_capacity(mut self, capacity: usize) -> Self {
+        self.outgoing_capacity = capacity;
+        self
+    }
+
+    /// Sets the number of incoming packets that are kept for each subscriber,
+    /// at least one packet is always kept.
+    pub fn incoming_capacity(mut self, capacity: usize) -> Self {
+        self.incoming_capacity = capacity.max(1);
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Constructs a new `Link` for the given `Sink` and `Stream` interfaces,
+    /// see [`Link::new`].
+    pub fn build<M, T, U>(
+        self,
+        outgoing: T,
+        incoming: U,
+        system_id: u8,
+        component_id: u8,
+    ) -> (Link<M>, impl Future<Output = ()>)
     where
-        T: Sink<Packet>,
-        U: Stream<Item = Packet>,
+        M: MessageExt,
+        T: Sink<Frame<M>>,
+        U: Stream,
+        U::Item: Into<DecodeEvent<M>>,
     {
-        let (sender, receiver) = flume::bounded(64);
-        let (mut publisher, subscriber) = broadcast::broadcast(64);
+        let (sender, receiver) = flume::bounded(self.outgoing_capacity);
+        let (mut publisher, subscriber) = broadcast::broadcast(self.incoming_capacity);
+        let stats = Arc::new(Mutex::new(LinkStats::default()));
 
-        // Remove the oldest message if the channel is full.
-        publisher.set_overflow(true);
+        // Remove the oldest message if the channel is full, unless we wait for
+        // the subscribers to catch up.
+        publisher.set_overflow(self.overflow != Overflow::BackPressure);
+
+        // Do not wait for a `LinkSender` to start receiving.
+        publisher.set_await_active(false);
 
         // Broadcast each incoming message.
+        let incoming_stats = stats.clone();
         let broadcast = async move {
             // Pin the stream so that we can call `.next()` on it.
             pin_mut!(incoming);
 
             // Broadcast channel does not implement `Sink`, so instead of forwarding,
             // we somehow need to publish incoming packets, This is why we loop.
-            while let Some(packet) = incoming.as_mut().map(Arc::new).next().await {
-                if let Err(_) = publisher.broadcast_direct(packet).await {
-                    // Publish packets, stop if all receivers are dropped.
+            while let Some(event) = incoming.next().await {
+                let event = event.into();
+                incoming_stats.lock().unwrap().record_incoming(&event, Instant::now());
+
+                // Raw packets of messages in the dialect, such as those of
+                // codecs that keep them raw, are decoded.
+                let incoming = match event.frame() {
+                    Some(Frame::Packet(packet)) => Incoming::Packet(Arc::new(packet)),
+                    Some(Frame::Raw(packet)) => match packet.decode() {
+                        Some(packet) => Incoming::Packet(Arc::new(packet)),
+                        None => Incoming::Raw(Arc::new(packet)),
+                    },
+                    None => continue,
+                };
+
+                // Publish packets, drop them while only `LinkSender`s are left,
+                // and stop if all receivers are dropped.
+                if publisher.broadcast_direct(incoming).await.is_err() && publisher.is_closed() {
                     break;
                 }
             }
         };
 
+        let outgoing_stats = stats.clone();
         let forward = async move {
             let mut header = Header { component_id, system_id, sequence: 255 };
-            let mut stream = receiver.into_stream().map(move |message| {
+            let mut stream = receiver.into_stream().map(move |outgoing| {
                 header.sequence = header.sequence.wrapping_add(1);
-                Ok(Packet { header, message })
+                let frame = match outgoing {
+                    Outgoing::Message(message) => Frame::Packet(Packet { header, message, version: Version::V2 }),
+                    Outgoing::Raw(packet) => Frame::Raw(RawPacket { header, ..packet }),
+                };
+
+                outgoing_stats.lock().unwrap().record_outgoing(&frame);
+                Ok(frame)
             });
 
             // Forward all packets in stream. Do not care how the "forwarding"
@@ -79,27 +433,31 @@ impl Link {
             let _ = outgoing.send_all(&mut stream).await;
         };
 
-        let link = Link { sender, subscriber };
+        let link = Link {
+            sender,
+            subscriber,
+            system_id,
+            component_id,
+            stats,
+            overflow: self.overflow,
+            missed: 0,
+        };
         let fut = join(forward, broadcast).map(|_| ());
 
         (link, fut)
     }
-
-    pub async fn send_message(&self, message: Message) -> Result<()> {
-        self.sender.send_async(message).await.map_err(From::from)
-    }
 }
 
-impl Sink<Message> for Link {
+impl<M> Sink<M> for Link<M> {
     type Error = Error;
 
     fn start_send(
         self: Pin<&mut Self>,
-        item: Message,
+        item: M,
     ) -> std::prelude::v1::Result<(), Self::Error> {
         Pin::new(&mut self.get_mut().sender.sink())
-            .start_send(item)
-            .map_err(From::from)
+            .start_send(Outgoing::Message(item))
+            .or(Err(Error::Send))
     }
 
     fn poll_close(
@@ -108,7 +466,7 @@ impl Sink<Message> for Link {
     ) -> Poll<std::prelude::v1::Result<(), Self::Error>> {
         Pin::new(&mut self.get_mut().sender.sink())
             .poll_close(cx)
-            .map_err(From::from)
+            .map_err(|_| Error::Send)
     }
 
     fn poll_flush(
@@ -117,7 +475,7 @@ impl Sink<Message> for Link {
     ) -> Poll<std::prelude::v1::Result<(), Self::Error>> {
         Pin::new(&mut self.get_mut().sender.sink())
             .poll_flush(cx)
-            .map_err(From::from)
+            .map_err(|_| Error::Send)
     }
 
     fn poll_ready(
@@ -126,15 +484,37 @@ impl Sink<Message> for Link {
     ) -> Poll<std::prelude::v1::Result<(), Self::Error>> {
         Pin::new(&mut self.get_mut().sender.sink())
             .poll_ready(cx)
-            .map_err(From::from)
+            .map_err(|_| Error::Send)
     }
 }
 
-impl Stream for Link {
-    type Item = Arc<Packet>;
+impl<M> Stream for Link<M> {
+    type Item = Arc<Packet<M>>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.get_mut().subscriber).poll_next(cx)
+        let this = self.get_mut();
+
+        // Count the packets that we missed, the stream of the subscriber skips
+        // them silently.
+        loop {
+            let incoming = match this.subscriber.try_recv() {
+                Ok(incoming) => incoming,
+                Err(broadcast::TryRecvError::Overflowed(missed)) => {
+                    this.missed += missed;
+                    continue;
+                }
+                Err(broadcast::TryRecvError::Closed) => break Poll::Ready(None),
+                Err(broadcast::TryRecvError::Empty) => match Pin::new(&mut this.subscriber).poll_next(cx) {
+                    Poll::Ready(Some(incoming)) => incoming,
+                    Poll::Ready(None) => break Poll::Ready(None),
+                    Poll::Pending => break Poll::Pending,
+                },
+            };
+
+            if let Incoming::Packet(packet) = incoming {
+                break Poll::Ready(Some(packet));
+            }
+        }
     }
 }
 
@@ -146,7 +526,7 @@ mod tests {
     #[test]
     fn link_future_resolves_when_all_links_are_dropped() {
         let sink = futures::sink::drain();
-        let stream = futures::stream::repeat(Default::default());
+        let stream = futures::stream::repeat(Packet::default());
         let (link, fut) = Link::new(sink, stream, 0, 0);
 
         let waker = futures::task::noop_waker();
@@ -161,5 +541,138 @@ mod tests {
         }
     }
 
+    #[test]
+    fn link_sender_keeps_the_link_future_running() {
+        let (packets, stream) = flume::unbounded::<Packet>();
+        let (link, fut) = Link::new(futures::sink::drain(), stream.into_stream(), 0, 0);
+        let sender = link.sender();
+
+        let waker = futures::task::noop_waker();
+        let mut context = Context::from_waker(&waker);
+
+        drop(link);
+
+        // The packets that arrive without a `Link` are dropped.
+        packets.send(Packet::default()).unwrap();
+        pin_mut!(fut);
+        assert!(fut.as_mut().poll(&mut context).is_pending());
+
+        let mut late = sender.link();
+        assert!(fut.as_mut().poll(&mut context).is_pending());
+        assert!(late.try_recv().is_err());
+
+        drop((late, sender));
+        packets.send(Packet::default()).unwrap();
+        assert!(fut.poll(&mut context).is_ready());
+    }
+
+    #[test]
+    fn link_builder_keeps_at_least_one_incoming_packet() {
+        let sink = futures::sink::drain();
+        let stream = futures::stream::iter(vec![Packet::default()]).chain(futures::stream::pending());
+        let (mut link, fut) = Link::builder().incoming_capacity(0).build(sink, stream, 0, 0);
+
+        let waker = futures::task::noop_waker();
+        let mut context = Context::from_waker(&waker);
+
+        pin_mut!(fut);
+        assert!(fut.poll(&mut context).is_pending());
+        assert!(link.try_recv().is_ok());
+    }
+
+    #[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+    #[test]
+    fn link_accepts_packets_addressed_to_it() {
+        use crate::dialect::COMMAND_ACK_DATA;
+
+        let (link, _) = Link::new(futures::sink::drain(), futures::stream::empty::<Packet>(), 255, 190);
+        let ack = |target_system, target_component| Packet {
+            message: Message::COMMAND_ACK(COMMAND_ACK_DATA {
+                target_system,
+                target_component,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(link.accepts(&Packet::default()));
+        assert!(link.accepts(&ack(255, 190)));
+        assert!(link.accepts(&ack(255, 0)));
+        assert!(link.accepts(&ack(0, 0)));
+        assert!(!link.accepts(&ack(254, 190)));
+        assert!(!link.accepts(&ack(255, 191)));
+    }
+
+    #[tokio::test]
+    async fn link_tracks_stats() {
+        let header = |sequence| Header { system_id: 1, component_id: 1, sequence };
+        let events = [0, 1, 4, 4]
+            .map(|sequence| DecodeEvent::Packet(Packet { header: header(sequence), ..Default::default() }))
+            .into_iter()
+            .chain([DecodeEvent::BadCrc { header: header(5), message_id: 0 }]);
+
+        let (link, fut) = Link::new(futures::sink::drain(), futures::stream::iter(events), 255, 190);
+        let mut updates = Box::pin(link.stats_updates(Duration::from_millis(10)));
+
+        link.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+        let packet_size = Packet::default().frame_len() as u64;
+
+        tokio::spawn(fut);
+
+        let stats = loop {
+            let stats = updates.next().await.unwrap();
+
+            if stats.packets_out == 1 && !stats.systems.is_empty() {
+                break stats;
+            }
+        };
+
+        let system = &stats.systems[&(1, 1)];
+        assert_eq!((system.received, system.lost, system.duplicates), (3, 2, 1));
+        assert_eq!((system.decode_errors, system.bytes_in), (1, 3 * packet_size));
+        assert_eq!(stats.bytes_out, packet_size);
+        assert_eq!(stats, link.stats());
+    }
+
+    fn sequenced(count: u8) -> impl Stream<Item = Packet> {
+        futures::stream::iter(0..count).map(|sequence| Packet {
+            header: Header { system_id: 1, component_id: 1, sequence },
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn link_overflow_policies() {
+        let builder = Link::builder().incoming_capacity(2);
+
+        // Slow subscribers miss the oldest packets.
+        let (mut link, fut) = builder.build(futures::sink::drain(), sequenced(5), 0, 0);
+        tokio::spawn(fut);
+
+        assert_eq!(link.next().await.unwrap().header.sequence, 3);
+        assert_eq!(link.missed(), 3);
+
+        // Or they are notified about it.
+        let builder = builder.overflow(Overflow::Notify);
+        let (mut link, fut) = builder.build(futures::sink::drain(), sequenced(5), 0, 0);
+        tokio::spawn(fut);
+
+        assert!(matches!(link.recv().await, Err(Error::Lagged(3))));
+        assert_eq!(link.recv().await.unwrap().header.sequence, 3);
+        assert_eq!(link.recv().await.unwrap().header.sequence, 4);
+        assert_eq!(link.missed(), 3);
+
+        // Or the connection waits for them.
+        let builder = builder.overflow(Overflow::BackPressure);
+        let (mut link, fut) = builder.build(futures::sink::drain(), sequenced(5), 0, 0);
+        tokio::spawn(fut);
+
+        for sequence in 0..5 {
+            assert_eq!(link.recv().await.unwrap().header.sequence, sequence);
+        }
+
+        assert_eq!(link.missed(), 0);
+    }
+
     // TODO: Test the case where sink and stream end, and the future is resolved.
 }
@@ -1,3 +1,9 @@
 pub mod error;
 pub mod link;
+pub mod reconnect;
+pub mod router;
+pub mod signing;
+pub mod stats;
+pub mod target;
+pub mod transport;
 pub mod wire;
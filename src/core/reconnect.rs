@@ -0,0 +1,331 @@
+//! Links that survive the loss of their connection.
+//!
+//! [`reconnect`] connects a [`Link`] exactly like
+//! [`transport::connect`](crate::transport::connect), but re-opens the
+//! connection whenever it is lost. The `Link`, its clones, and every
+//! `Component` built on them stay valid across reconnections, and the state
+//! of the connection can be observed through the returned [`Connection`].
+use async_broadcast as broadcast;
+use crate::{
+    dialect::MessageExt,
+    link::Link,
+    transport::{open, Address},
+    wire::{DecodeEvent, Frame, PacketCodec},
+};
+use futures_time::task::sleep;
+use futures_util::{
+    future::{join, select, BoxFuture, Either, FutureExt},
+    pin_mut, Stream, StreamExt,
+};
+use std::{
+    io::{ErrorKind, Result},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// The delays between consecutive failed connection attempts.
+///
+/// The delay starts at the minimum, and doubles with every failed attempt
+/// until it reaches the maximum. A lost connection is re-opened immediately if
+/// it received a packet, otherwise it counts as a failed attempt, so that a
+/// peer that accepts and drops connections is not flooded with attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self { min, max: max.max(min) }
+    }
+
+    /// Returns the delay after the given number of failed attempts.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.min.saturating_mul(factor).min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100), Duration::from_secs(5))
+    }
+}
+
+/// A change in the state of a reconnecting connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Connected,
+    Disconnected,
+
+    /// An attempt to connect failed, the next one is made after `delay`.
+    Failed { attempt: u32, kind: ErrorKind, delay: Duration },
+}
+
+/// The state of a reconnecting connection.
+///
+/// `Connection` is a stream of the [`ConnectionEvent`]s that happen after it
+/// was created, or cloned.
+#[derive(Debug, Clone)]
+pub struct Connection {
+    connected: Arc<AtomicBool>,
+    events: broadcast::Receiver<ConnectionEvent>,
+}
+
+impl Connection {
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+}
+
+impl Stream for Connection {
+    type Item = ConnectionEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().events).poll_next(cx)
+    }
+}
+
+/// Connects a link to the given connection string, and re-opens the
+/// connection with the given backoff whenever it is lost. See the
+/// [transport](crate::transport) documentation for the accepted formats.
+///
+/// The connection is opened by the returned future, which must be spawned
+/// for the link to work. The future resolves when all of the links, and
+/// their [`LinkSender`](crate::link::LinkSender)s, are dropped.
+///
+/// Outgoing messages are dropped while there is no connection, since they
+/// would be stale by the time the connection is re-opened.
+pub fn reconnect<M>(
+    address: &str,
+    codec: PacketCodec<M>,
+    backoff: Backoff,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, Connection, BoxFuture<'static, ()>)>
+where
+    M: MessageExt + Clone + Send + Sync + 'static,
+{
+    let address = address.parse()?;
+
+    // The link sends to, and receives from, the connection of the moment.
+    let (outgoing_sender, outgoing) = flume::bounded(64);
+    let (incoming, incoming_receiver) = flume::bounded(64);
+    let (link, fut) = Link::new(
+        outgoing_sender.into_sink(),
+        incoming_receiver.into_stream(),
+        system_id,
+        component_id,
+    );
+
+    let (mut publisher, events) = broadcast::broadcast(16);
+    publisher.set_overflow(true);
+
+    let state = State { connected: Arc::new(AtomicBool::new(false)), publisher };
+    let connection = Connection { connected: state.connected.clone(), events };
+
+    let driver = drive(address, codec, backoff, outgoing, incoming, state);
+    let fut = join(fut, driver).map(|_| ()).boxed();
+
+    Ok((link, connection, fut))
+}
+
+struct State {
+    connected: Arc<AtomicBool>,
+    publisher: broadcast::Sender<ConnectionEvent>,
+}
+
+impl State {
+    fn notify(&self, event: ConnectionEvent) {
+        self.connected.store(event == ConnectionEvent::Connected, Ordering::Relaxed);
+
+        // Nobody might be listening, that is fine.
+        let _ = self.publisher.try_broadcast(event);
+    }
+}
+
+/// Opens connections to the address, and pumps packets between them and the
+/// link until all of the links are dropped.
+async fn drive<M>(
+    address: Address,
+    codec: PacketCodec<M>,
+    backoff: Backoff,
+    outgoing: flume::Receiver<Frame<M>>,
+    incoming: flume::Sender<DecodeEvent<M>>,
+    state: State,
+) where
+    M: MessageExt + Clone + Send + Sync + 'static,
+{
+    let mut attempt = 0;
+
+    loop {
+        let opening = open(&address, codec.clone());
+        let closed = discard(&outgoing);
+        pin_mut!(opening, closed);
+
+        let (sink, stream) = match select(opening, closed).await {
+            Either::Left((Ok(connection), _)) => connection,
+            Either::Left((Err(error), _)) => {
+                attempt += 1;
+                let delay = backoff.delay(attempt);
+                state.notify(ConnectionEvent::Failed { attempt, kind: error.kind(), delay });
+
+                // Wait for the next attempt, unless the links are dropped.
+                match wait(delay, &outgoing).await {
+                    true => continue,
+                    false => return,
+                }
+            }
+            Either::Right(_) => return,
+        };
+
+        state.notify(ConnectionEvent::Connected);
+
+        // Pump packets until either direction ends, or fails. The backoff is
+        // reset by the first frame that decodes.
+        let mut received = false;
+        let upstream = outgoing.stream().map(Ok).forward(sink);
+        let downstream = stream
+            .inspect(|event| {
+                received |= matches!(event, DecodeEvent::Packet(_) | DecodeEvent::Raw(_) | DecodeEvent::UnknownMessage(_))
+            })
+            .map(Ok)
+            .forward(incoming.sink());
+        select(upstream, downstream).await;
+
+        state.notify(ConnectionEvent::Disconnected);
+
+        if outgoing.is_disconnected() || incoming.is_disconnected() {
+            return;
+        }
+
+        if received {
+            attempt = 0;
+        } else {
+            attempt += 1;
+
+            if !wait(backoff.delay(attempt), &outgoing).await {
+                return;
+            }
+        }
+    }
+}
+
+/// Waits for the given delay, and returns `false` if all of the links are
+/// dropped in the meantime.
+async fn wait<T>(delay: Duration, outgoing: &flume::Receiver<T>) -> bool {
+    let waiting = sleep(delay.into());
+    let closed = discard(outgoing);
+    pin_mut!(waiting, closed);
+
+    matches!(select(waiting, closed).await, Either::Left(_))
+}
+
+/// Drops the outgoing packets until all of the links are dropped.
+async fn discard<T>(outgoing: &flume::Receiver<T>) {
+    while outgoing.recv_async().await.is_ok() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dialect::Message, wire::Packet};
+    use futures_util::SinkExt;
+    use tokio::net::TcpListener;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<_> = (1..=5).map(|attempt| backoff.delay(attempt).as_millis()).collect();
+
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn reconnect_reports_failed_attempts() {
+        // Find a port that nobody listens to.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("tcp:{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(20));
+        let (_link, mut connection, fut) =
+            reconnect::<Message>(&address, PacketCodec::default(), backoff, 255, 1).unwrap();
+
+        tokio::spawn(fut);
+
+        for expected in 1..=3 {
+            match connection.next().await.unwrap() {
+                ConnectionEvent::Failed { attempt, .. } => assert_eq!(attempt, expected),
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+
+        assert!(!connection.is_connected());
+    }
+
+    #[tokio::test]
+    async fn reconnect_backs_off_from_connections_that_drop_at_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("tcp:{}", listener.local_addr().unwrap());
+
+        let backoff = Backoff::new(Duration::from_millis(50), Duration::from_millis(200));
+        let (_link, _connection, fut) =
+            reconnect::<Message>(&address, PacketCodec::default(), backoff, 255, 1).unwrap();
+
+        tokio::spawn(fut);
+
+        // Accept connections and drop them before they carry a packet.
+        let start = std::time::Instant::now();
+
+        for _ in 0..3 {
+            drop(listener.accept().await.unwrap());
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn reconnect_keeps_links_valid() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("tcp:{}", listener.local_addr().unwrap());
+
+        let (link, mut connection, fut) =
+            reconnect(&address, PacketCodec::default(), Backoff::default(), 255, 1).unwrap();
+
+        tokio::spawn(fut);
+
+        let mut packets = link.clone();
+
+        for _ in 0..2 {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (reader, writer) = socket.into_split();
+            let mut reader = FramedRead::new(reader, PacketCodec::default());
+            let mut writer = FramedWrite::new(writer, PacketCodec::default());
+
+            assert_eq!(connection.next().await, Some(ConnectionEvent::Connected));
+            assert!(connection.is_connected());
+
+            // Packets flow in both directions through the same link.
+            link.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+            let packet = reader.next().await.unwrap().unwrap();
+            assert_eq!(packet.header.system_id, 255);
+
+            writer.send(Packet::default()).await.unwrap();
+            let packet = packets.next().await.unwrap();
+            assert!(matches!(packet.message, Message::HEARTBEAT(_)));
+
+            // Lose the connection.
+            drop((reader, writer));
+            assert_eq!(connection.next().await, Some(ConnectionEvent::Disconnected));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Forwards packets between many connections.
+//!
+//! A [`Router`] learns which systems and components live behind each of its
+//! interfaces from the packets it receives, and forwards every packet
+//! according to the MAVLink routing rules:
+//!
+//! * Packets are never sent back to the interface they came from.
+//! * Packets without a target, or with a broadcast target system, are sent to
+//!   every other interface.
+//! * Packets with a target are only sent to the interfaces that the target
+//!   has been seen on. Packets to unknown targets are dropped.
+//!
+//! Raw packets of messages that are not in the dialect are forwarded too, by
+//! the same rules if the message is known to have a target, and to every
+//! other interface if it is not.
+//!
+//! Every interface has its own queue of outgoing packets, so a slow interface
+//! does not hold up the others. Packets to an interface whose queue is full
+//! are dropped.
+use crate::{
+    dialect::{Header, Message, MessageExt},
+    link::Link,
+    target::Target,
+    transport::{open, BoxSink},
+    wire::{DecodeEvent, Frame, PacketCodec},
+};
+use flume::TrySendError;
+use futures_util::{
+    future::{self, join, join_all, BoxFuture, Future, FutureExt},
+    stream::{self, BoxStream},
+    Sink, SinkExt, Stream, StreamExt,
+};
+use std::{
+    collections::HashSet,
+    io::{Error, ErrorKind, Result},
+};
+
+/// Forwards packets between its interfaces, see the [module
+/// documentation](self) for the rules.
+pub struct Router<M = Message> {
+    sinks: Vec<BoxSink<M>>,
+    streams: Vec<BoxStream<'static, Frame<M>>>,
+    links: Vec<BoxFuture<'static, ()>>,
+    queue_capacity: usize,
+}
+
+impl<M> Default for Router<M> {
+    fn default() -> Self {
+        Self { sinks: Vec::new(), streams: Vec::new(), links: Vec::new(), queue_capacity: 64 }
+    }
+}
+
+impl<M> Router<M>
+where
+    M: MessageExt + Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of outgoing packets that are queued for each interface
+    /// before packets to it are dropped, which is 64 by default.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Adds an interface with the given `Sink` and `Stream` halves, the
+    /// `Stream` yields either packets or decode events.
+    ///
+    /// Raw packets are forwarded byte-for-byte, decoded packets are encoded
+    /// again by the sinks they are forwarded to, without their signatures.
+    pub fn add<T, U>(&mut self, sink: T, stream: U)
+    where
+        T: Sink<Frame<M>, Error = Error> + Send + 'static,
+        U: Stream + Send + 'static,
+        U::Item: Into<DecodeEvent<M>>,
+    {
+        let frames = stream.filter_map(|item| future::ready(item.into().frame()));
+
+        self.sinks.push(Box::pin(sink));
+        self.streams.push(frames.boxed());
+    }
+
+    /// Opens a connection to the given connection string, and adds it as an
+    /// interface. See the [transport](crate::transport) documentation for the
+    /// accepted formats.
+    ///
+    /// The packets of the connection are kept raw, see
+    /// [`PacketCodec::keep_raw`], so they are forwarded byte-for-byte.
+    pub async fn connect(&mut self, address: &str, codec: PacketCodec<M>) -> Result<()> {
+        let (sink, stream) = open(&address.parse()?, codec.keep_raw()).await?;
+        self.add(sink, stream);
+        Ok(())
+    }
+
+    /// Adds a local interface, and returns a `Link` that sends and receives
+    /// through it with the given ids.
+    pub fn link(&mut self, system_id: u8, component_id: u8) -> Link<M> {
+        let (outgoing, from_link) = flume::bounded(64);
+        let (to_link, incoming) = flume::bounded(64);
+        let (link, fut) = Link::new(
+            outgoing.into_sink(),
+            incoming.into_stream(),
+            system_id,
+            component_id,
+        );
+
+        let sink = to_link
+            .into_sink()
+            .sink_map_err(|_| Error::from(ErrorKind::BrokenPipe));
+
+        self.add(sink, from_link.into_stream());
+        self.links.push(fut.boxed());
+
+        link
+    }
+
+    /// Returns a future that forwards packets between the interfaces, which
+    /// must be spawned for the router to work.
+    ///
+    /// Interfaces whose sinks fail are not sent to anymore. The future
+    /// resolves when the streams of all of the interfaces are exhausted.
+    pub fn run(self) -> impl Future<Output = ()> {
+        let Router { sinks, streams, links, queue_capacity } = self;
+
+        // Drain the queue of each interface into its sink.
+        let (queues, senders): (Vec<_>, Vec<_>) = sinks
+            .into_iter()
+            .map(|sink| {
+                let (sender, queue) = flume::bounded(queue_capacity);
+                (queue.into_stream().map(Ok).forward(sink).map(|_| ()), sender)
+            })
+            .unzip();
+
+        join(join(route(senders, streams), join_all(queues)), join_all(links)).map(|_| ())
+    }
+}
+
+async fn route<M>(queues: Vec<flume::Sender<Frame<M>>>, streams: Vec<BoxStream<'static, Frame<M>>>)
+where
+    M: MessageExt + Clone,
+{
+    let mut routes = Routes::new(queues.len());
+    let mut queues: Vec<_> = queues.into_iter().map(Some).collect();
+
+    // Tag each frame with the interface it came from.
+    let streams = streams
+        .into_iter()
+        .enumerate()
+        .map(|(interface, stream)| stream.map(move |frame| (interface, frame)));
+
+    let mut incoming = stream::select_all(streams);
+
+    while let Some((source, frame)) = incoming.next().await {
+        routes.learn(source, frame.header());
+        let target = frame.target();
+
+        for (interface, slot) in queues.iter_mut().enumerate() {
+            if !routes.forwards(source, interface, target) {
+                continue;
+            }
+
+            // Drop the packet if the interface is behind, and stop sending to
+            // it once its sink failed.
+            if let Some(queue) = slot {
+                if let Err(TrySendError::Disconnected(_)) = queue.try_send(frame.clone()) {
+                    *slot = None;
+                }
+            }
+        }
+    }
+}
+
+/// The systems and components that have been seen on each interface.
+#[derive(Debug)]
+struct Routes {
+    seen: Vec<HashSet<(u8, u8)>>,
+}
+
+impl Routes {
+    fn new(interfaces: usize) -> Self {
+        Self { seen: vec![HashSet::new(); interfaces] }
+    }
+
+    fn learn(&mut self, interface: usize, header: Header) {
+        self.seen[interface].insert((header.system_id, header.component_id));
+    }
+
+    /// Returns whether a packet with the given target, that came from the
+    /// source interface, is forwarded to the destination interface.
+    fn forwards(&self, source: usize, destination: usize, target: Option<Target>) -> bool {
+        if source == destination {
+            return false;
+        }
+
+        match target {
+            Some(target) if target.system_id != 0 => self.seen[destination]
+                .iter()
+                .any(|&(system_id, component_id)| target.includes(system_id, component_id)),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dialect::Version,
+        link::Incoming,
+        wire::{Packet, RawPacket},
+    };
+
+    fn header(system_id: u8, component_id: u8) -> Header {
+        Header { system_id, component_id, sequence: 0 }
+    }
+
+    fn target(system_id: u8, component_id: u8) -> Option<Target> {
+        Some(Target { system_id, component_id })
+    }
+
+    #[test]
+    fn routes_follow_the_routing_rules() {
+        let mut routes = Routes::new(3);
+        routes.learn(0, header(1, 1));
+        routes.learn(0, header(1, 154));
+        routes.learn(1, header(255, 190));
+
+        // Broadcasts go everywhere except the source.
+        assert!(!routes.forwards(1, 1, None));
+        assert!(routes.forwards(1, 0, None));
+        assert!(routes.forwards(1, 2, None));
+        assert!(routes.forwards(1, 2, target(0, 0)));
+
+        // Targets go where they have been seen.
+        assert!(routes.forwards(1, 0, target(1, 1)));
+        assert!(routes.forwards(1, 0, target(1, 0)));
+        assert!(!routes.forwards(1, 2, target(1, 1)));
+        assert!(!routes.forwards(1, 0, target(1, 2)));
+        assert!(!routes.forwards(1, 0, target(2, 0)));
+
+        // Never back to the source, even if the target lives there.
+        assert!(!routes.forwards(0, 0, target(1, 1)));
+    }
+
+    #[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+    #[tokio::test]
+    async fn router_forwards_packets_between_interfaces() {
+        use crate::dialect::COMMAND_LONG_DATA;
+
+        let mut router = Router::new();
+        let mut interfaces = Vec::new();
+
+        for _ in 0..3 {
+            let (sender, outgoing) = flume::unbounded();
+            let (incoming, receiver) = flume::unbounded();
+            let sink = incoming
+                .into_sink()
+                .sink_map_err(|_| Error::from(ErrorKind::BrokenPipe));
+
+            router.add(sink, outgoing.into_stream());
+            interfaces.push((sender, receiver));
+        }
+
+        let gcs = router.link(255, 190);
+        let mut gcs_packets = gcs.clone();
+        tokio::spawn(router.run());
+
+        let heartbeat = |system_id, component_id| Frame::Packet(Packet {
+            header: header(system_id, component_id),
+            message: Message::HEARTBEAT(Default::default()),
+            ..Default::default()
+        });
+
+        // Introduce an autopilot on the first, and a camera on the second
+        // interface.
+        interfaces[0].0.send(heartbeat(1, 1)).unwrap();
+        interfaces[1].0.send(heartbeat(1, 100)).unwrap();
+
+        let ids = |frame: Frame| (frame.header().system_id, frame.header().component_id);
+        assert_eq!(ids(interfaces[0].1.recv_async().await.unwrap()), (1, 100));
+        assert_eq!(ids(interfaces[1].1.recv_async().await.unwrap()), (1, 1));
+
+        // The order of packets from different interfaces is not defined.
+        let mut seen = vec![
+            ids(interfaces[2].1.recv_async().await.unwrap()),
+            ids(interfaces[2].1.recv_async().await.unwrap()),
+        ];
+        seen.sort();
+        assert_eq!(seen, vec![(1, 1), (1, 100)]);
+
+        let mut seen = vec![
+            gcs_packets.next().await.unwrap().header.component_id,
+            gcs_packets.next().await.unwrap().header.component_id,
+        ];
+        seen.sort();
+        assert_eq!(seen, vec![1, 100]);
+
+        // A command to the autopilot only reaches the first interface.
+        let command = |target_component| {
+            Message::COMMAND_LONG(COMMAND_LONG_DATA {
+                target_system: 1,
+                target_component,
+                ..Default::default()
+            })
+        };
+
+        gcs.send_message(command(1)).await.unwrap();
+        gcs.send_message(command(2)).await.unwrap();
+        gcs.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+
+        let packet = interfaces[0].1.recv_async().await.unwrap().packet().unwrap();
+        assert!(matches!(packet.message, Message::COMMAND_LONG(_)));
+
+        // The command to the unknown component is dropped, the heartbeat is not.
+        for (_, receiver) in &interfaces {
+            let packet = receiver.recv_async().await.unwrap().packet().unwrap();
+            assert!(matches!(packet.message, Message::HEARTBEAT(_)));
+            assert_eq!(packet.header.system_id, 255);
+        }
+    }
+
+    #[tokio::test]
+    async fn router_does_not_wait_for_slow_interfaces() {
+        let mut router = Router::new().queue_capacity(4);
+        let (sender, outgoing) = flume::unbounded();
+        let (incoming, receiver) = flume::unbounded();
+
+        let stuck = futures::sink::unfold((), |_, _: Frame| future::pending());
+        let sink = incoming
+            .into_sink()
+            .sink_map_err(|_| Error::from(ErrorKind::BrokenPipe));
+
+        router.add(futures::sink::drain().sink_map_err(|_| Error::from(ErrorKind::BrokenPipe)), outgoing.into_stream());
+        router.add(stuck, futures::stream::pending::<Packet>());
+        router.add(sink, futures::stream::pending::<Packet>());
+
+        tokio::spawn(router.run());
+
+        // The second interface never accepts a packet, its queue overflows.
+        for sequence in 0..32 {
+            let header = Header { sequence, ..header(1, 1) };
+            sender.send(Packet { header, ..Default::default() }).unwrap();
+
+            assert_eq!(receiver.recv_async().await.unwrap().header().sequence, sequence);
+        }
+    }
+
+    #[tokio::test]
+    async fn router_forwards_raw_packets() {
+        let mut router = Router::new();
+        let (sender, outgoing) = flume::unbounded();
+        let (incoming, receiver) = flume::unbounded();
+        let sink = incoming
+            .into_sink()
+            .sink_map_err(|_| Error::from(ErrorKind::BrokenPipe));
+
+        router.add(sink, outgoing.into_stream());
+
+        let mut gcs = router.link(255, 190);
+        tokio::spawn(router.run());
+
+        let raw = RawPacket {
+            header: header(1, 42),
+            version: Version::V2,
+            message_id: 53_000,
+            incompatibility_flags: 0,
+            compatibility_flags: 0,
+            payload: vec![1, 2, 3],
+            checksum: 0x1234,
+            signature: None,
+            crc_extra: None,
+        };
+
+        // Raw packets reach local links as they are.
+        sender.send(Frame::<Message>::Raw(raw.clone())).unwrap();
+
+        let Incoming::Raw(received) = gcs.recv_incoming().await.unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert_eq!(*received, raw);
+
+        // And local links send them with their own header.
+        gcs.send_raw(raw.clone()).await.unwrap();
+
+        let Frame::Raw(sent) = receiver.recv_async().await.unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert_eq!((sent.header.system_id, sent.header.component_id), (255, 190));
+        assert_eq!(sent.payload, raw.payload);
+    }
+}
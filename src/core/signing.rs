@@ -0,0 +1,171 @@
+use crate::dialect::{Header, MessageId};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The id of `RADIO_STATUS`, which radios inject into the stream unsigned.
+const RADIO_STATUS_ID: MessageId = 109;
+
+/// Seconds between the UNIX epoch and 1st of January 2015 GMT, the epoch of
+/// signing timestamps.
+const SIGNING_EPOCH: u64 = 1_420_070_400;
+
+/// A new stream is rejected if its timestamp is older than this.
+const REPLAY_WINDOW: u64 = 60 * 100_000;
+
+pub type SecretKey = [u8; 32];
+
+/// Returns the current time as a signing timestamp, in units of 10
+/// microseconds since 1st of January 2015 GMT.
+pub fn timestamp_now() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .saturating_sub(Duration::from_secs(SIGNING_EPOCH));
+
+    (since_epoch.as_micros() / 10) as u64
+}
+
+/// Decides what happens to unsigned packets while signing is enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum UnsignedPolicy {
+    /// Accept every unsigned packet.
+    Accept,
+
+    /// Reject every unsigned packet except `RADIO_STATUS`, since radios can
+    /// not sign the packets they inject.
+    #[default]
+    Reject,
+
+    /// Accept unsigned packets whose message id satisfies the predicate.
+    Filter(fn(MessageId) -> bool),
+}
+
+/// The MAVLink 2 signing state of a connection.
+///
+/// Outgoing frames are signed with the secret key and link id, and incoming
+/// signed frames are verified against the secret key. Incoming timestamps are
+/// tracked per (system, component, link) stream to reject replayed frames.
+///
+/// Clones share the same state, so the read and write halves of a connection
+/// agree on the current timestamp.
+#[derive(Debug, Clone)]
+pub struct Signing {
+    key: SecretKey,
+    link_id: u8,
+    unsigned: UnsignedPolicy,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    timestamp: u64,
+    streams: HashMap<(u8, u8, u8), u64>,
+}
+
+impl Signing {
+    pub const SIGNATURE_SIZE: usize = 13;
+
+    pub fn new(key: SecretKey, link_id: u8) -> Self {
+        let state = State { timestamp: timestamp_now(), ..Default::default() };
+
+        Self {
+            key,
+            link_id,
+            unsigned: Default::default(),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Sets the policy for unsigned incoming packets.
+    pub fn unsigned(mut self, policy: UnsignedPolicy) -> Self {
+        self.unsigned = policy;
+        self
+    }
+
+    pub fn key(&self) -> &SecretKey {
+        &self.key
+    }
+
+    pub fn link_id(&self) -> u8 {
+        self.link_id
+    }
+
+    /// Returns the latest timestamp that has been sent or received.
+    pub fn timestamp(&self) -> u64 {
+        self.state.lock().unwrap().timestamp
+    }
+
+    /// Returns the signature block for the given frame.
+    ///
+    /// The frame must start with the magic byte and end with the checksum, and
+    /// must already have the signed incompatibility flag set.
+    pub(crate) fn sign(&self, frame: &[u8]) -> [u8; Self::SIGNATURE_SIZE] {
+        // The timestamp must increase with every packet sent.
+        let timestamp = {
+            let mut state = self.state.lock().unwrap();
+            state.timestamp = timestamp_now().max(state.timestamp + 1);
+            state.timestamp
+        };
+
+        let mut block = [0; Self::SIGNATURE_SIZE];
+        block[0] = self.link_id;
+        block[1..7].copy_from_slice(&timestamp.to_le_bytes()[..6]);
+
+        let signature = self.signature(frame, &block[..7]);
+        block[7..].copy_from_slice(&signature);
+
+        block
+    }
+
+    /// Verifies the signature block of a frame sent by the given header.
+    pub(crate) fn verify(&self, frame: &[u8], block: &[u8], header: Header) -> bool {
+        if self.signature(frame, &block[..7]) != block[7..Self::SIGNATURE_SIZE] {
+            return false;
+        }
+
+        let mut bytes = [0; 8];
+        bytes[..6].copy_from_slice(&block[1..7]);
+        let timestamp = u64::from_le_bytes(bytes);
+
+        let mut state = self.state.lock().unwrap();
+        let stream = (header.system_id, header.component_id, block[0]);
+
+        match state.streams.get(&stream) {
+            // Replayed, or reordered, packet of a known stream.
+            Some(last) if timestamp <= *last => return false,
+            // A new stream must be roughly as recent as we are.
+            None if timestamp + REPLAY_WINDOW < state.timestamp => return false,
+            _ => {}
+        }
+
+        state.streams.insert(stream, timestamp);
+        state.timestamp = state.timestamp.max(timestamp);
+
+        true
+    }
+
+    /// Returns whether an unsigned packet with the given id is accepted.
+    pub(crate) fn accepts_unsigned(&self, message_id: MessageId) -> bool {
+        match self.unsigned {
+            UnsignedPolicy::Accept => true,
+            UnsignedPolicy::Reject => message_id == RADIO_STATUS_ID,
+            UnsignedPolicy::Filter(predicate) => predicate(message_id),
+        }
+    }
+
+    fn signature(&self, frame: &[u8], link_id_and_timestamp: &[u8]) -> [u8; 6] {
+        let hash = Sha256::new()
+            .chain_update(self.key)
+            .chain_update(frame)
+            .chain_update(link_id_and_timestamp)
+            .finalize();
+
+        let mut signature = [0; 6];
+        signature.copy_from_slice(&hash[..6]);
+        signature
+    }
+}
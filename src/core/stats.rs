@@ -0,0 +1,172 @@
+//! Statistics of the packets that go through a [`Link`](crate::link::Link).
+use crate::{
+    dialect::{Header, MessageExt},
+    wire::{DecodeEvent, Frame},
+};
+use std::{collections::HashMap, time::Instant};
+
+/// A sequence number at most this far behind the last one is a late packet,
+/// any other sequence number is ahead of the last one, after a gap.
+const REORDER_WINDOW: u8 = 16;
+
+/// The statistics of the packets received from one system and component.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemStats {
+    pub received: u64,
+    /// The number of packets that were skipped in the sequence.
+    pub lost: u64,
+    pub duplicates: u64,
+    /// The number of bytes received, signatures are not counted.
+    pub bytes_in: u64,
+    pub decode_errors: u64,
+    pub last_seen: Option<Instant>,
+    last_sequence: Option<u8>,
+}
+
+impl SystemStats {
+    /// Returns the percentage of the packets that were lost.
+    pub fn loss(&self) -> f64 {
+        match self.received + self.lost {
+            0 => 0.0,
+            total => self.lost as f64 * 100.0 / total as f64,
+        }
+    }
+
+    fn record(&mut self, sequence: u8, bytes: usize, now: Instant) {
+        self.last_seen = Some(now);
+
+        let Some(last) = self.last_sequence else {
+            self.received += 1;
+            self.bytes_in += bytes as u64;
+            self.last_sequence = Some(sequence);
+            return;
+        };
+
+        if sequence == last {
+            self.duplicates += 1;
+            return;
+        }
+
+        self.received += 1;
+        self.bytes_in += bytes as u64;
+
+        match last.wrapping_sub(sequence) {
+            // A late packet, which was counted as lost.
+            behind if behind <= REORDER_WINDOW => self.lost = self.lost.saturating_sub(1),
+            // A gap, whose length is only known modulo 256.
+            _ => {
+                self.lost += sequence.wrapping_sub(last).wrapping_sub(1) as u64;
+                self.last_sequence = Some(sequence);
+            }
+        }
+    }
+}
+
+/// The statistics of a link, see [`Link::stats`](crate::link::Link::stats).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkStats {
+    /// The statistics of each (system, component) that has been heard from.
+    pub systems: HashMap<(u8, u8), SystemStats>,
+    pub packets_out: u64,
+    /// The number of bytes sent, counted as MAVLink 2 frames whatever the
+    /// codec frames them with. Signatures are not counted.
+    pub bytes_out: u64,
+    /// The number of invalid frames that could not be attributed to a known
+    /// system, such as noise.
+    pub decode_errors: u64,
+}
+
+impl LinkStats {
+    pub(crate) fn record_incoming<M: MessageExt>(&mut self, event: &DecodeEvent<M>, now: Instant) {
+        match event {
+            DecodeEvent::Packet(packet) => {
+                self.system(packet.header).record(packet.header.sequence, packet.frame_len(), now)
+            }
+            DecodeEvent::Raw(packet) | DecodeEvent::UnknownMessage(packet) => {
+                self.system(packet.header).record(packet.header.sequence, packet.frame_len(), now)
+            }
+            DecodeEvent::BadCrc { header, .. }
+            | DecodeEvent::InvalidMessage { header, .. }
+            | DecodeEvent::BadSignature { header, .. } => {
+                // The header of an invalid frame might be noise, so the error
+                // is only attributed to systems that have been heard from.
+                match self.systems.get_mut(&(header.system_id, header.component_id)) {
+                    Some(system) => system.decode_errors += 1,
+                    None => self.decode_errors += 1,
+                }
+            }
+            DecodeEvent::Truncated { .. } => self.decode_errors += 1,
+        }
+    }
+
+    pub(crate) fn record_outgoing<M: MessageExt>(&mut self, frame: &Frame<M>) {
+        self.packets_out += 1;
+        self.bytes_out += frame.frame_len() as u64;
+    }
+
+    fn system(&mut self, header: Header) -> &mut SystemStats {
+        self.systems.entry((header.system_id, header.component_id)).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dialect::Message, wire::Packet};
+
+    type Event = DecodeEvent<Message>;
+
+    fn record(sequences: impl IntoIterator<Item = u8>) -> SystemStats {
+        let mut stats = SystemStats::default();
+        let now = Instant::now();
+
+        for sequence in sequences {
+            stats.record(sequence, 10, now);
+        }
+
+        stats
+    }
+
+    #[test]
+    fn system_stats_track_the_sequence() {
+        let stats = record([0, 1, 2, 3]);
+        assert_eq!((stats.received, stats.lost, stats.duplicates), (4, 0, 0));
+        assert_eq!(stats.bytes_in, 40);
+
+        // Gaps, including one that wraps around.
+        let stats = record([250, 252, 255, 3]);
+        assert_eq!((stats.received, stats.lost), (4, 6));
+        assert_eq!(stats.loss(), 60.0);
+
+        // Duplicates are not received twice.
+        let stats = record([7, 7, 8, 8]);
+        assert_eq!((stats.received, stats.duplicates), (2, 2));
+
+        // Late packets are not lost.
+        let stats = record([1, 3, 2, 4]);
+        assert_eq!((stats.received, stats.lost), (4, 0));
+
+        // Long gaps are lost, and the sequence goes on after them.
+        let stats = record((0..10).chain(210..=255).chain(0..10));
+        assert_eq!((stats.received, stats.lost), (66, 200));
+    }
+
+    #[test]
+    fn link_stats_attribute_decode_errors() {
+        let mut stats = LinkStats::default();
+        let now = Instant::now();
+        let header = Header { system_id: 1, component_id: 1, sequence: 0 };
+        let noise = Header { system_id: 42, component_id: 42, sequence: 0 };
+
+        stats.record_incoming(&Event::Packet(Packet { header, ..Default::default() }), now);
+        stats.record_incoming(&Event::BadCrc { header, message_id: 0 }, now);
+        stats.record_incoming(&Event::BadCrc { header: noise, message_id: 0 }, now);
+        stats.record_incoming(&Event::Truncated { len: 3 }, now);
+
+        assert_eq!(stats.systems.len(), 1);
+        assert_eq!(stats.systems[&(1, 1)].received, 1);
+        assert_eq!(stats.systems[&(1, 1)].decode_errors, 1);
+        assert_eq!(stats.systems[&(1, 1)].last_seen, Some(now));
+        assert_eq!(stats.decode_errors, 2);
+    }
+}
@@ -0,0 +1,236 @@
+//! The targets of packets, which are used to route them.
+use crate::{
+    dialect::{MessageExt, MessageId, Version},
+    wire::{Frame, Packet, RawPacket},
+};
+
+/// The system and component that a packet is addressed to, zero stands for
+/// every system or component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub system_id: u8,
+    pub component_id: u8,
+}
+
+impl Target {
+    /// Returns whether the target includes the given system and component.
+    pub fn includes(&self, system_id: u8, component_id: u8) -> bool {
+        (self.system_id == 0 || self.system_id == system_id)
+            && (self.component_id == 0 || self.component_id == component_id)
+    }
+
+    /// Returns the target of the given payload, using the offsets of the
+    /// target fields of its message.
+    fn from_payload(message_id: MessageId, payload: &[u8]) -> Option<Self> {
+        // MAVLink 2 truncates the trailing zeros of payloads, so missing bytes
+        // are zeros, which is a broadcast.
+        let byte = |offset: usize| payload.get(offset).copied().unwrap_or(0);
+        let (system, component) = offsets(message_id)?;
+
+        Some(Target {
+            system_id: byte(system),
+            component_id: component.map(byte).unwrap_or(0),
+        })
+    }
+}
+
+impl<M: MessageExt> Packet<M> {
+    /// Returns the target of the packet, or `None` if its message can not be
+    /// addressed.
+    pub fn target(&self) -> Option<Target> {
+        let mut payload = [0; 255];
+        let len = self.message.ser(Version::V2, &mut payload);
+        Target::from_payload(self.message.message_id(), &payload[..len])
+    }
+
+    /// Returns whether the packet is addressed to the given system and
+    /// component, packets without a target are addressed to everyone.
+    pub fn is_addressed_to(&self, system_id: u8, component_id: u8) -> bool {
+        self.target()
+            .is_none_or(|target| target.includes(system_id, component_id))
+    }
+}
+
+impl RawPacket {
+    /// Returns the target of the packet, or `None` if its message can not be
+    /// addressed.
+    pub fn target(&self) -> Option<Target> {
+        Target::from_payload(self.message_id, &self.payload)
+    }
+}
+
+impl<M: MessageExt> Frame<M> {
+    /// Returns the target of the frame, or `None` if its message can not be
+    /// addressed.
+    pub fn target(&self) -> Option<Target> {
+        match self {
+            Frame::Packet(packet) => packet.target(),
+            Frame::Raw(packet) => packet.target(),
+        }
+    }
+}
+
+/// Returns the payload offsets of the `target_system` and `target_component`
+/// fields of the message with the given id.
+///
+/// The offsets are taken from the MAVLink definitions, so that they do not
+/// depend on the dialect the packet is decoded with. Messages that are not in
+/// the definitions can not be addressed.
+pub fn offsets(message_id: MessageId) -> Option<(usize, Option<usize>)> {
+    let offsets = match message_id {
+        4 => (12, Some(13)), // PING
+        5 => (0, None), // CHANGE_OPERATOR_CONTROL
+        11 => (4, None), // SET_MODE
+        20 => (2, Some(3)), // PARAM_REQUEST_READ
+        21 => (0, Some(1)), // PARAM_REQUEST_LIST
+        23 => (4, Some(5)), // PARAM_SET
+        37 => (4, Some(5)), // MISSION_REQUEST_PARTIAL_LIST
+        38 => (4, Some(5)), // MISSION_WRITE_PARTIAL_LIST
+        39 => (32, Some(33)), // MISSION_ITEM
+        40 => (2, Some(3)), // MISSION_REQUEST
+        41 => (2, Some(3)), // MISSION_SET_CURRENT
+        43 => (0, Some(1)), // MISSION_REQUEST_LIST
+        44 => (2, Some(3)), // MISSION_COUNT
+        45 => (0, Some(1)), // MISSION_CLEAR_ALL
+        47 => (0, Some(1)), // MISSION_ACK
+        48 => (12, None), // SET_GPS_GLOBAL_ORIGIN
+        50 => (18, Some(19)), // PARAM_MAP_RC
+        51 => (2, Some(3)), // MISSION_REQUEST_INT
+        54 => (24, Some(25)), // SAFETY_SET_ALLOWED_AREA
+        66 => (2, Some(3)), // REQUEST_DATA_STREAM
+        69 => (10, None), // MANUAL_CONTROL
+        70 => (16, Some(17)), // RC_CHANNELS_OVERRIDE
+        73 => (32, Some(33)), // MISSION_ITEM_INT
+        75 => (30, Some(31)), // COMMAND_INT
+        76 => (30, Some(31)), // COMMAND_LONG
+        77 => (8, Some(9)), // COMMAND_ACK
+        80 => (2, Some(3)), // COMMAND_CANCEL
+        82 => (36, Some(37)), // SET_ATTITUDE_TARGET
+        84 => (50, Some(51)), // SET_POSITION_TARGET_LOCAL_NED
+        86 => (50, Some(51)), // SET_POSITION_TARGET_GLOBAL_INT
+        110 => (1, Some(2)), // FILE_TRANSFER_PROTOCOL
+        111 => (16, Some(17)), // TIMESYNC
+        117 => (4, Some(5)), // LOG_REQUEST_LIST
+        119 => (10, Some(11)), // LOG_REQUEST_DATA
+        121 => (0, Some(1)), // LOG_ERASE
+        122 => (0, Some(1)), // LOG_REQUEST_END
+        123 => (0, Some(1)), // GPS_INJECT_DATA
+        126 => (79, Some(80)), // SERIAL_CONTROL
+        139 => (41, Some(42)), // SET_ACTUATOR_CONTROL_TARGET
+        151 => (6, Some(7)), // SET_MAG_OFFSETS
+        154 => (6, Some(7)), // DIGICAM_CONFIGURE
+        155 => (4, Some(5)), // DIGICAM_CONTROL
+        156 => (0, Some(1)), // MOUNT_CONFIGURE
+        157 => (12, Some(13)), // MOUNT_CONTROL
+        158 => (12, Some(13)), // MOUNT_STATUS
+        160 => (8, Some(9)), // FENCE_POINT
+        161 => (0, Some(1)), // FENCE_FETCH_POINT
+        175 => (14, Some(15)), // RALLY_POINT
+        176 => (0, Some(1)), // RALLY_FETCH_POINT
+        179 => (26, None), // CAMERA_STATUS
+        180 => (42, None), // CAMERA_FEEDBACK
+        183 => (0, Some(1)), // AUTOPILOT_VERSION_REQUEST
+        184 => (4, Some(5)), // REMOTE_LOG_DATA_BLOCK
+        185 => (4, Some(5)), // REMOTE_LOG_BLOCK_STATUS
+        186 => (0, Some(1)), // LED_CONTROL
+        200 => (40, Some(41)), // GIMBAL_REPORT
+        201 => (12, Some(13)), // GIMBAL_CONTROL
+        214 => (6, Some(7)), // GIMBAL_TORQUE_CMD_REPORT
+        216 => (0, Some(1)), // GOPRO_GET_REQUEST
+        218 => (0, Some(1)), // GOPRO_SET_REQUEST
+        243 => (52, None), // SET_HOME_POSITION
+        248 => (3, Some(4)), // V2_EXTENSION
+        256 => (8, Some(9)), // SETUP_SIGNING
+        258 => (0, Some(1)), // PLAY_TUNE
+        266 => (2, Some(3)), // LOGGING_DATA
+        267 => (2, Some(3)), // LOGGING_DATA_ACKED
+        268 => (2, Some(3)), // LOGGING_ACK
+        282 => (32, Some(33)), // GIMBAL_MANAGER_SET_ATTITUDE
+        284 => (30, Some(31)), // GIMBAL_DEVICE_SET_ATTITUDE
+        285 => (38, Some(39)), // GIMBAL_DEVICE_ATTITUDE_STATUS
+        286 => (50, Some(51)), // AUTOPILOT_STATE_FOR_GIMBAL_DEVICE
+        287 => (20, Some(21)), // GIMBAL_MANAGER_SET_PITCHYAW
+        288 => (20, Some(21)), // GIMBAL_MANAGER_SET_MANUAL_CONTROL
+        320 => (2, Some(3)), // PARAM_EXT_REQUEST_READ
+        321 => (0, Some(1)), // PARAM_EXT_REQUEST_LIST
+        323 => (0, Some(1)), // PARAM_EXT_SET
+        385 => (2, Some(3)), // TUNNEL
+        386 => (4, Some(5)), // CAN_FRAME
+        387 => (4, Some(5)), // CANFD_FRAME
+        388 => (32, Some(33)), // CAN_FILTER_MODIFY
+        400 => (4, Some(5)), // PLAY_TUNE_V2
+        401 => (4, Some(5)), // SUPPORTED_TUNES
+        412 => (4, Some(5)), // REQUEST_EVENT
+        413 => (4, Some(5)), // RESPONSE_EVENT_ERROR
+        11000 => (4, Some(5)), // DEVICE_OP_READ
+        11002 => (4, Some(5)), // DEVICE_OP_WRITE
+        11033 => (16, Some(17)), // OSD_PARAM_CONFIG
+        11035 => (4, Some(5)), // OSD_PARAM_SHOW_CONFIG
+        12900 => (0, Some(1)), // OPEN_DRONE_ID_BASIC_ID
+        12901 => (30, Some(31)), // OPEN_DRONE_ID_LOCATION
+        12902 => (4, Some(5)), // OPEN_DRONE_ID_AUTHENTICATION
+        12903 => (0, Some(1)), // OPEN_DRONE_ID_SELF_ID
+        12904 => (28, Some(29)), // OPEN_DRONE_ID_SYSTEM
+        12905 => (0, Some(1)), // OPEN_DRONE_ID_OPERATOR_ID
+        12915 => (0, Some(1)), // OPEN_DRONE_ID_MESSAGE_PACK
+        12919 => (16, Some(17)), // OPEN_DRONE_ID_SYSTEM_UPDATE
+        50004 => (8, Some(9)), // CUBEPILOT_FIRMWARE_UPDATE_START
+        50005 => (4, Some(5)), // CUBEPILOT_FIRMWARE_UPDATE_RESP
+        _ => return None,
+    };
+
+    Some(offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Message;
+
+    fn packet(message: Message) -> Packet {
+        Packet { message, ..Default::default() }
+    }
+
+    // The minimal dialects have no messages with targets.
+    #[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+    #[test]
+    fn packets_have_targets() {
+        use crate::dialect::{COMMAND_ACK_DATA, COMMAND_LONG_DATA, MANUAL_CONTROL_DATA};
+
+        let command = packet(Message::COMMAND_LONG(COMMAND_LONG_DATA {
+            target_system: 1,
+            target_component: 2,
+            ..Default::default()
+        }));
+
+        let ack = packet(Message::COMMAND_ACK(COMMAND_ACK_DATA {
+            target_system: 255,
+            target_component: 190,
+            ..Default::default()
+        }));
+
+        let manual = packet(Message::MANUAL_CONTROL(MANUAL_CONTROL_DATA {
+            target: 3,
+            ..Default::default()
+        }));
+
+        assert_eq!(command.target(), Some(Target { system_id: 1, component_id: 2 }));
+        assert_eq!(ack.target(), Some(Target { system_id: 255, component_id: 190 }));
+        assert_eq!(manual.target(), Some(Target { system_id: 3, component_id: 0 }));
+    }
+
+    #[test]
+    fn heartbeats_have_no_target() {
+        assert_eq!(packet(Message::HEARTBEAT(Default::default())).target(), None);
+    }
+
+    #[test]
+    fn target_includes_broadcasts() {
+        let target = Target { system_id: 1, component_id: 0 };
+
+        assert!(target.includes(1, 1));
+        assert!(target.includes(1, 190));
+        assert!(!target.includes(2, 1));
+        assert!(Target { system_id: 0, component_id: 0 }.includes(2, 1));
+    }
+}
@@ -0,0 +1,668 @@
+//! Constructors that connect a [`Link`] over UDP, TCP or serial ports.
+//!
+//! Every constructor returns a `Link` and a future, exactly like
+//! [`Link::new`], and the future must be spawned for the link to work. The
+//! given [`PacketCodec`] is used for every packet of the connection, so it
+//! decides on the MAVLink version, signing, and the dialect of the link.
+//!
+//! Invalid frames never end a connection, they can be observed through the
+//! [`DecodeStats`](crate::wire::DecodeStats) of the codec, and the
+//! [`stats`](Link::stats) of the link.
+//!
+//! [`connect`] accepts connection strings:
+//!
+//! * `udpin:<bind>` (or `udp:<bind>`): a UDP server, see [`udp_server`].
+//! * `udpout:<remote>`: a UDP client, see [`udp_client`].
+//! * `tcpin:<bind>`: a TCP server, see [`tcp_server`].
+//! * `tcp:<remote>` (or `tcpout:<remote>`): a TCP client, see [`tcp_client`].
+//! * `serial:<path>:<baud>`: a serial port, see `serial`, which requires the
+//!   `serial` feature.
+//!
+//! The future of a link resolves when its connection is lost, see
+//! [`reconnect`](crate::reconnect) for links that re-open their connections.
+use crate::{
+    dialect::MessageExt,
+    link::Link,
+    target::Target,
+    wire::{DecodeEvent, Frame, PacketCodec},
+};
+use futures_util::{
+    future::{self, BoxFuture, Future, FutureExt},
+    sink,
+    stream::BoxStream,
+    Sink, Stream, StreamExt,
+};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::net::{lookup_host, TcpListener, TcpStream, UdpSocket};
+use tokio_util::{
+    bytes::BytesMut,
+    codec::{Encoder, FramedRead, FramedWrite},
+    udp::UdpFramed,
+};
+
+/// UDP servers forget the peers that they have not heard from for this long.
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type BoxSink<M> = Pin<Box<dyn Sink<Frame<M>, Error = Error> + Send>>;
+
+/// The address of a connection, parsed from a connection string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    UdpIn(String),
+    UdpOut(String),
+    TcpIn(String),
+    TcpOut(String),
+    Serial { path: String, baud: u32 },
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid address `{s}`"));
+        let (scheme, rest) = s.split_once(':').ok_or_else(invalid)?;
+
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+
+        let address = match scheme {
+            "udp" | "udpin" => Address::UdpIn(rest.into()),
+            "udpout" => Address::UdpOut(rest.into()),
+            "tcpin" => Address::TcpIn(rest.into()),
+            "tcp" | "tcpout" => Address::TcpOut(rest.into()),
+            "serial" => {
+                // The path itself might contain colons, the baud rate can not.
+                let (path, baud) = rest.rsplit_once(':').ok_or_else(invalid)?;
+                let baud = baud.parse().map_err(|_| invalid())?;
+                Address::Serial { path: path.into(), baud }
+            }
+            _ => return Err(invalid()),
+        };
+
+        Ok(address)
+    }
+}
+
+/// Connects a link to the given connection string, see the [module
+/// documentation](self) for the accepted formats.
+pub async fn connect<M>(
+    address: &str,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, BoxFuture<'static, ()>)>
+where
+    M: MessageExt + Clone + Send + Sync + 'static,
+{
+    let (sink, stream) = open(&address.parse()?, codec).await?;
+    let (link, fut) = Link::new(sink, stream, system_id, component_id);
+
+    Ok((link, fut.boxed()))
+}
+
+/// Opens the sink and stream halves of a connection to the given address.
+pub async fn open<M>(
+    address: &Address,
+    codec: PacketCodec<M>,
+) -> Result<(BoxSink<M>, BoxStream<'static, DecodeEvent<M>>)>
+where
+    M: MessageExt + Clone + Send + Sync + 'static,
+{
+    fn boxed<M, T, U>((sink, stream): (T, U)) -> (BoxSink<M>, BoxStream<'static, DecodeEvent<M>>)
+    where
+        T: Sink<Frame<M>, Error = Error> + Send + 'static,
+        U: Stream<Item = DecodeEvent<M>> + Send + 'static,
+    {
+        (Box::pin(sink), stream.boxed())
+    }
+
+    match address {
+        Address::UdpIn(bind) => open_udp_server(bind, PEER_TIMEOUT, codec).await.map(boxed),
+        Address::UdpOut(remote) => open_udp_client(remote, codec).await.map(boxed),
+        Address::TcpIn(bind) => open_tcp_server(bind, codec).await.map(boxed),
+        Address::TcpOut(remote) => open_tcp_client(remote, codec).await.map(boxed),
+        #[cfg(feature = "serial")]
+        Address::Serial { path, baud } => open_serial(path, *baud, codec).map(boxed),
+        #[cfg(not(feature = "serial"))]
+        Address::Serial { .. } => Err(Error::new(
+            ErrorKind::Unsupported,
+            "serial ports require the `serial` feature",
+        )),
+    }
+}
+
+/// Connects a link to a UDP server at the given remote address.
+///
+/// The socket is bound to an ephemeral port, and every packet is sent to the
+/// remote address.
+pub async fn udp_client<M>(
+    remote: &str,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt + Clone,
+{
+    let (sink, stream) = open_udp_client(remote, codec).await?;
+    Ok(Link::new(sink, stream, system_id, component_id))
+}
+
+/// Binds a UDP server to the given address, and connects a link to it.
+///
+/// The server learns its peers, and the systems behind each peer, from the
+/// packets it receives. Packets that target a known system are sent to the
+/// peer that system was last seen from, every other packet is sent to all of
+/// the peers. Packets are dropped until the first peer is learned.
+///
+/// Peers that have not been heard from for [`PEER_TIMEOUT`] are forgotten,
+/// see [`udp_server_with_peer_timeout`] to change that.
+pub async fn udp_server<M>(
+    bind: &str,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt + Clone,
+{
+    udp_server_with_peer_timeout(bind, PEER_TIMEOUT, codec, system_id, component_id).await
+}
+
+/// Same as [`udp_server`], but forgets peers after the given timeout.
+pub async fn udp_server_with_peer_timeout<M>(
+    bind: &str,
+    peer_timeout: Duration,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt + Clone,
+{
+    let (sink, stream) = open_udp_server(bind, peer_timeout, codec).await?;
+    Ok(Link::new(sink, stream, system_id, component_id))
+}
+
+/// Connects a link to a TCP server at the given remote address.
+pub async fn tcp_client<M>(
+    remote: &str,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt,
+{
+    let (sink, stream) = open_tcp_client(remote, codec).await?;
+    Ok(Link::new(sink, stream, system_id, component_id))
+}
+
+/// Binds a TCP server to the given address, and connects a link to the first
+/// client that connects to it.
+///
+/// This function does not return until a client connects.
+pub async fn tcp_server<M>(
+    bind: &str,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt,
+{
+    let (sink, stream) = open_tcp_server(bind, codec).await?;
+    Ok(Link::new(sink, stream, system_id, component_id))
+}
+
+/// Opens the serial port at the given path, and connects a link to it.
+#[cfg(feature = "serial")]
+pub fn serial<M>(
+    path: &str,
+    baud: u32,
+    codec: PacketCodec<M>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(Link<M>, impl Future<Output = ()>)>
+where
+    M: MessageExt,
+{
+    let (sink, stream) = open_serial(path, baud, codec)?;
+    Ok(Link::new(sink, stream, system_id, component_id))
+}
+
+/// Ends a decoded stream on the first I/O error.
+fn events<M, S>(stream: S) -> impl Stream<Item = DecodeEvent<M>>
+where
+    S: Stream<Item = Result<DecodeEvent<M>>>,
+{
+    stream
+        .take_while(|result| future::ready(result.is_ok()))
+        .filter_map(|result| future::ready(result.ok()))
+}
+
+/// Skips the I/O errors of a decoded UDP stream.
+///
+/// A UDP socket keeps working after an error, which is often caused by a
+/// single peer, such as an ICMP port unreachable from a peer that went away.
+fn udp_events<M, S>(stream: S) -> impl Stream<Item = DecodeEvent<M>>
+where
+    S: Stream<Item = Result<(DecodeEvent<M>, SocketAddr)>>,
+{
+    stream.filter_map(|result| future::ready(result.ok().map(|(event, _)| event)))
+}
+
+/// Sends each frame over a UDP socket to the peers returned by `destinations`.
+///
+/// A peer that can not be sent to is passed to `failed`, the frame is still
+/// sent to the other peers, and the sink keeps working.
+fn udp_sink<M, D, F>(
+    socket: Arc<UdpSocket>,
+    codec: PacketCodec<M>,
+    destinations: D,
+    failed: F,
+) -> impl Sink<Frame<M>, Error = Error>
+where
+    M: MessageExt,
+    D: FnMut(&Frame<M>) -> Vec<SocketAddr>,
+    F: FnMut(SocketAddr),
+{
+    sink::unfold(
+        (socket, codec, destinations, failed),
+        |(socket, mut codec, mut destinations, mut failed), frame: Frame<M>| async move {
+            let peers = destinations(&frame);
+            let mut datagram = BytesMut::new();
+            codec.encode(frame, &mut datagram)?;
+
+            for peer in peers {
+                if socket.send_to(&datagram, peer).await.is_err() {
+                    failed(peer);
+                }
+            }
+
+            Ok((socket, codec, destinations, failed))
+        },
+    )
+}
+
+/// The peers of a UDP server, and the systems behind them.
+#[derive(Debug)]
+struct Peers {
+    timeout: Duration,
+    last_seen: HashMap<SocketAddr, Instant>,
+    systems: HashMap<u8, SocketAddr>,
+}
+
+impl Peers {
+    fn new(timeout: Duration) -> Self {
+        Self { timeout, last_seen: HashMap::new(), systems: HashMap::new() }
+    }
+
+    fn learn(&mut self, peer: SocketAddr, system_id: u8, now: Instant) {
+        self.last_seen.insert(peer, now);
+        self.systems.insert(system_id, peer);
+    }
+
+    /// Returns the peers that a packet with the given target is sent to.
+    fn destinations(&mut self, target: Option<Target>, now: Instant) -> Vec<SocketAddr> {
+        self.expire(now);
+
+        let peer = target
+            .filter(|target| target.system_id != 0)
+            .and_then(|target| self.systems.get(&target.system_id));
+
+        match peer {
+            Some(peer) => vec![*peer],
+            None => self.last_seen.keys().copied().collect(),
+        }
+    }
+
+    /// Forgets a peer, and the systems behind it.
+    fn forget(&mut self, peer: SocketAddr) {
+        self.last_seen.remove(&peer);
+        self.systems.retain(|_, system_peer| *system_peer != peer);
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        self.last_seen.retain(|_, last_seen| now.duration_since(*last_seen) < timeout);
+
+        let last_seen = &self.last_seen;
+        self.systems.retain(|_, peer| last_seen.contains_key(peer));
+    }
+}
+
+async fn resolve(address: &str) -> Result<SocketAddr> {
+    lookup_host(address)
+        .await?
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("could not resolve `{address}`")))
+}
+
+async fn open_udp_client<M>(
+    remote: &str,
+    codec: PacketCodec<M>,
+) -> Result<(impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)>
+where
+    M: MessageExt + Clone,
+{
+    let remote = resolve(remote).await?;
+    let bind = if remote.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+
+    // Share the UDP socket between the two halves, the codec clones share
+    // their state.
+    let socket = Arc::new(UdpSocket::bind(bind).await?);
+    let stream = udp_events(UdpFramed::new(socket.clone(), codec.clone().events()));
+    let sink = udp_sink(socket, codec, move |_| vec![remote], |_| {});
+
+    Ok((sink, stream))
+}
+
+async fn open_udp_server<M>(
+    bind: &str,
+    peer_timeout: Duration,
+    codec: PacketCodec<M>,
+) -> Result<(impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)>
+where
+    M: MessageExt + Clone,
+{
+    // Share the UDP socket between the two halves, the codec clones share
+    // their state.
+    let socket = Arc::new(UdpSocket::bind(bind).await?);
+    let stream = UdpFramed::new(socket.clone(), codec.clone().events());
+
+    let peers = Arc::new(Mutex::new(Peers::new(peer_timeout)));
+    let learned = peers.clone();
+    let failed = peers.clone();
+
+    // Send each packet to the peer of its target system, or to every peer, and
+    // forget the peers that can not be sent to.
+    let sink = udp_sink(
+        socket,
+        codec,
+        move |frame| peers.lock().unwrap().destinations(frame.target(), Instant::now()),
+        move |peer| failed.lock().unwrap().forget(peer),
+    );
+
+    // Learn the peers, and the systems behind them, from the incoming packets.
+    let stream = stream.map(move |result| {
+        result.map(|(event, peer)| {
+            let header = match &event {
+                DecodeEvent::Packet(packet) => Some(packet.header),
+                DecodeEvent::Raw(packet) | DecodeEvent::UnknownMessage(packet) => Some(packet.header),
+                _ => None,
+            };
+
+            if let Some(header) = header {
+                learned.lock().unwrap().learn(peer, header.system_id, Instant::now());
+            }
+
+            (event, peer)
+        })
+    });
+
+    Ok((sink, udp_events(stream)))
+}
+
+async fn open_tcp_client<M>(
+    remote: &str,
+    codec: PacketCodec<M>,
+) -> Result<(impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)>
+where
+    M: MessageExt,
+{
+    let connection = TcpStream::connect(remote).await?;
+    Ok(split_tcp(connection, codec))
+}
+
+async fn open_tcp_server<M>(
+    bind: &str,
+    codec: PacketCodec<M>,
+) -> Result<(impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)>
+where
+    M: MessageExt,
+{
+    let listener = TcpListener::bind(bind).await?;
+    let (connection, _) = listener.accept().await?;
+    Ok(split_tcp(connection, codec))
+}
+
+fn split_tcp<M>(
+    connection: TcpStream,
+    codec: PacketCodec<M>,
+) -> (impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)
+where
+    M: MessageExt,
+{
+    // Split the connection into two halves, the codec clones share their state.
+    let (reader, writer) = connection.into_split();
+    let sink = FramedWrite::new(writer, codec.clone());
+    let stream = events(FramedRead::new(reader, codec.events()));
+
+    (sink, stream)
+}
+
+#[cfg(feature = "serial")]
+fn open_serial<M>(
+    path: &str,
+    baud: u32,
+    codec: PacketCodec<M>,
+) -> Result<(impl Sink<Frame<M>, Error = Error>, impl Stream<Item = DecodeEvent<M>>)>
+where
+    M: MessageExt,
+{
+    use tokio_serial::SerialPortBuilderExt;
+    use tokio_util::codec::Decoder;
+
+    // Create a Serial connection, and split it into two halves.
+    let port = tokio_serial::new(path, baud).open_native_async()?;
+    let (sink, stream) = codec.events().framed(port).split();
+
+    Ok((sink, events(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Message;
+    use futures_util::stream;
+
+    #[test]
+    fn address_parses_connection_strings() {
+        let parse = |s: &str| s.parse::<Address>().ok();
+
+        assert_eq!(parse("udpin:0.0.0.0:14550"), Some(Address::UdpIn("0.0.0.0:14550".into())));
+        assert_eq!(parse("udp:0.0.0.0:14550"), Some(Address::UdpIn("0.0.0.0:14550".into())));
+        assert_eq!(parse("udpout:10.0.0.1:14550"), Some(Address::UdpOut("10.0.0.1:14550".into())));
+        assert_eq!(parse("tcpin:0.0.0.0:5760"), Some(Address::TcpIn("0.0.0.0:5760".into())));
+        assert_eq!(parse("tcp:127.0.0.1:5760"), Some(Address::TcpOut("127.0.0.1:5760".into())));
+        assert_eq!(
+            parse("serial:/dev/ttyUSB0:57600"),
+            Some(Address::Serial { path: "/dev/ttyUSB0".into(), baud: 57600 }),
+        );
+
+        assert_eq!(parse("serial:/dev/ttyUSB0"), None);
+        assert_eq!(parse("serial:/dev/ttyUSB0:fast"), None);
+        assert_eq!(parse("ftp:127.0.0.1:21"), None);
+        assert_eq!(parse("tcp:"), None);
+        assert_eq!(parse("127.0.0.1:5760"), None);
+    }
+
+    #[tokio::test]
+    async fn udp_server_replies_to_learned_peers() {
+        // Find a free port for the server.
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        drop(socket);
+
+        let (server, server_connection) =
+            udp_server(&address, PacketCodec::default(), 1, 1).await.unwrap();
+        let (client, client_connection) =
+            udp_client(&address, PacketCodec::default(), 255, 1).await.unwrap();
+
+        tokio::spawn(server_connection);
+        tokio::spawn(client_connection);
+
+        let mut server_packets = server.clone();
+        let mut client_packets = client.clone();
+
+        client.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+        let packet = server_packets.next().await.unwrap();
+        assert_eq!(packet.header.system_id, 255);
+
+        server.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+        let packet = client_packets.next().await.unwrap();
+        assert_eq!(packet.header.system_id, 1);
+    }
+
+    #[tokio::test]
+    async fn tcp_client_connects_to_tcp_server() {
+        // Find a free port for the server.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let remote = format!("tcp:{address}");
+        let server = tcp_server(&address, PacketCodec::default(), 1, 1);
+        let client = connect(&remote, PacketCodec::default(), 255, 1);
+        let (server, client) = futures::future::join(server, client).await;
+
+        let (server, server_connection) = server.unwrap();
+        let (client, client_connection) = client.unwrap();
+
+        tokio::spawn(server_connection);
+        tokio::spawn(client_connection);
+
+        let mut server_packets = server.clone();
+        client.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+
+        let packet = server_packets.next().await.unwrap();
+        assert_eq!(packet.header.system_id, 255);
+    }
+
+    #[test]
+    fn peers_route_targeted_packets() {
+        let now = Instant::now();
+        let a: SocketAddr = "10.0.0.1:14550".parse().unwrap();
+        let b: SocketAddr = "10.0.0.2:14550".parse().unwrap();
+
+        let mut peers = Peers::new(Duration::from_secs(10));
+        assert!(peers.destinations(None, now).is_empty());
+
+        peers.learn(a, 1, now);
+        peers.learn(b, 2, now);
+
+        let target = |system_id| Some(Target { system_id, component_id: 0 });
+        let mut all = peers.destinations(None, now);
+        all.sort();
+
+        assert_eq!(all, vec![a, b]);
+        assert_eq!(peers.destinations(target(1), now), vec![a]);
+        assert_eq!(peers.destinations(target(2), now), vec![b]);
+        assert_eq!(peers.destinations(target(3), now).len(), 2);
+        assert_eq!(peers.destinations(target(0), now).len(), 2);
+
+        // The system moves to another peer.
+        peers.learn(a, 2, now);
+        assert_eq!(peers.destinations(target(2), now), vec![a]);
+
+        // Peer `b` goes stale.
+        let later = now + Duration::from_secs(6);
+        peers.learn(a, 1, later);
+
+        let much_later = now + Duration::from_secs(12);
+        assert_eq!(peers.destinations(None, much_later), vec![a]);
+        assert_eq!(peers.destinations(target(1), much_later), vec![a]);
+    }
+
+    #[test]
+    fn peers_forget_failing_peers() {
+        let now = Instant::now();
+        let a: SocketAddr = "10.0.0.1:14550".parse().unwrap();
+        let b: SocketAddr = "10.0.0.2:14550".parse().unwrap();
+
+        let mut peers = Peers::new(Duration::from_secs(10));
+        peers.learn(a, 1, now);
+        peers.learn(b, 2, now);
+        peers.forget(a);
+
+        let target = Some(Target { system_id: 1, component_id: 0 });
+        assert_eq!(peers.destinations(None, now), vec![b]);
+        assert_eq!(peers.destinations(target, now), vec![b]);
+    }
+
+    #[tokio::test]
+    async fn udp_events_skip_io_errors() {
+        let peer: SocketAddr = "10.0.0.1:14550".parse().unwrap();
+        let event = || DecodeEvent::<Message>::Truncated { len: 1 };
+        let unreachable = || Error::new(ErrorKind::ConnectionRefused, "port unreachable");
+
+        let results = vec![Ok((event(), peer)), Err(unreachable()), Ok((event(), peer))];
+        let events: Vec<_> = udp_events(stream::iter(results)).collect().await;
+
+        assert_eq!(events.len(), 2);
+    }
+
+    #[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+    #[tokio::test]
+    async fn udp_server_routes_packets_to_systems() {
+        use crate::dialect::COMMAND_LONG_DATA;
+        use tokio::time::timeout;
+
+        // Find a free port for the server.
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        drop(socket);
+
+        let (server, server_connection) =
+            udp_server(&address, PacketCodec::default(), 255, 1).await.unwrap();
+        let (first, first_connection) =
+            udp_client(&address, PacketCodec::default(), 1, 1).await.unwrap();
+        let (second, second_connection) =
+            udp_client(&address, PacketCodec::default(), 2, 1).await.unwrap();
+
+        tokio::spawn(server_connection);
+        tokio::spawn(first_connection);
+        tokio::spawn(second_connection);
+
+        let mut server_packets = server.clone();
+        let mut first_packets = first.clone();
+        let mut second_packets = second.clone();
+
+        // Introduce the systems to the server.
+        first.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+        server_packets.next().await.unwrap();
+        second.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+        server_packets.next().await.unwrap();
+
+        let command = Message::COMMAND_LONG(COMMAND_LONG_DATA {
+            target_system: 2,
+            ..Default::default()
+        });
+
+        server.send_message(command).await.unwrap();
+        server.send_message(Message::HEARTBEAT(Default::default())).await.unwrap();
+
+        let packet = second_packets.next().await.unwrap();
+        assert!(matches!(packet.message, Message::COMMAND_LONG(_)));
+
+        // The first system only receives the broadcast heartbeat.
+        let packet = first_packets.next().await.unwrap();
+        assert!(matches!(packet.message, Message::HEARTBEAT(_)));
+
+        let packet = second_packets.next().await.unwrap();
+        assert!(matches!(packet.message, Message::HEARTBEAT(_)));
+
+        let nothing = timeout(Duration::from_millis(100), first_packets.next()).await;
+        assert!(nothing.is_err());
+    }
+}
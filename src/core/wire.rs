@@ -1,7 +1,18 @@
-use crate::dialect::{Header, Message, MessageExt};
-use std::io::{Error, ErrorKind::InvalidData};
+use crate::{
+    dialect::{Header, Message, MessageExt, MessageId, Version},
+    signing::Signing,
+};
+use std::{
+    io::{Error, ErrorKind::InvalidData},
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use mavlink::{MAVLinkV2MessageRaw as RawPacket, MavlinkVersion::V2, MAV_STX_V2 as MAGIC_BYTE};
+use mavlink::{error::ParserError, MAV_STX as MAGIC_BYTE_V1, MAV_STX_V2 as MAGIC_BYTE_V2};
 
 use tokio_util::{
     bytes::{Buf, BytesMut},
@@ -10,18 +21,125 @@ use tokio_util::{
 
 use crc16::{State, MCRF4XX};
 
-pub type DecoderResult = std::result::Result<Packet, std::io::Error>;
+const HEADER_SIZE_V1: usize = 5;
+const HEADER_SIZE_V2: usize = 9;
+const CKSUM_SIZE: usize = 2;
+const MAX_PAYLOAD_SIZE: usize = 255;
+const IFLAG_SIGNED: u8 = 0x01;
+
+pub type DecoderResult<M = Message> = std::result::Result<Packet<M>, std::io::Error>;
 
+/// A decoded packet of the dialect `M`.
 #[derive(Debug, Clone)]
-pub struct Packet {
+pub struct Packet<M = Message> {
+    pub header: Header,
+    pub message: M,
+    /// The MAVLink version an incoming packet was framed with.
+    ///
+    /// Outgoing packets are framed with the version that the codec picks, see
+    /// [`EncodeVersion`], so the field is ignored when encoding, and
+    /// [`Link`](crate::link::Link) always sets it to MAVLink 2.
+    pub version: Version,
+}
+
+/// A packet whose message is not in the dialect, carried undecoded.
+///
+/// Raw packets keep everything that is needed to encode them byte-for-byte as
+/// they were received, so that they can be forwarded and logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPacket {
     pub header: Header,
-    pub message: Message,
+    pub version: Version,
+    pub message_id: MessageId,
+    pub incompatibility_flags: u8,
+    pub compatibility_flags: u8,
+    pub payload: Vec<u8>,
+    pub checksum: u16,
+    pub signature: Option<[u8; Signing::SIGNATURE_SIZE]>,
+    /// The CRC extra of the message, if it has been registered with
+    /// [`PacketCodec::with_crc_extra`].
+    pub crc_extra: Option<u8>,
 }
 
-impl Packet {
-    const HEADER_SIZE: usize = 9;
-    const CKSUM_SIZE: usize = 2;
-    const IFLAG_SIGNED: u8 = 0x01;
+/// A packet of the dialect `M`, or a raw packet of a message that is not in
+/// it, as it is sent through a [`Link`](crate::link::Link) or forwarded by a
+/// [`Router`](crate::router::Router).
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum Frame<M = Message> {
+    Packet(Packet<M>),
+    Raw(RawPacket),
+}
+
+impl<M> Frame<M> {
+    pub fn header(&self) -> Header {
+        match self {
+            Frame::Packet(packet) => packet.header,
+            Frame::Raw(packet) => packet.header,
+        }
+    }
+
+    /// Returns the packet if the frame is not raw.
+    pub fn packet(self) -> Option<Packet<M>> {
+        match self {
+            Frame::Packet(packet) => Some(packet),
+            Frame::Raw(_) => None,
+        }
+    }
+}
+
+impl<M: MessageExt> Frame<M> {
+    /// Returns the size of the frame, without a signature.
+    pub fn frame_len(&self) -> usize {
+        match self {
+            Frame::Packet(packet) => packet.frame_len(),
+            Frame::Raw(packet) => packet.frame_len(),
+        }
+    }
+}
+
+impl<M> From<Packet<M>> for Frame<M> {
+    fn from(packet: Packet<M>) -> Self {
+        Frame::Packet(packet)
+    }
+}
+
+impl<M> From<RawPacket> for Frame<M> {
+    fn from(packet: RawPacket) -> Self {
+        Frame::Raw(packet)
+    }
+}
+
+impl<M: MessageExt> Packet<M> {
+    /// Returns the size of the frame of the packet, without a signature.
+    pub fn frame_len(&self) -> usize {
+        let mut payload = [0; MAX_PAYLOAD_SIZE];
+        let len = self.message.ser(self.version, &mut payload);
+        frame_len(self.version, len)
+    }
+}
+
+impl RawPacket {
+    /// Returns the size of the frame of the packet, without a signature.
+    pub fn frame_len(&self) -> usize {
+        frame_len(self.version, self.payload.len())
+    }
+
+    /// Decodes the payload into a packet of the dialect `M`, or returns `None`
+    /// if the message is not in the dialect or invalid.
+    pub fn decode<M: MessageExt>(&self) -> Option<Packet<M>> {
+        let message = M::parse(self.version, self.message_id, &self.payload).ok()?;
+        Some(Packet { header: self.header, message, version: self.version })
+    }
+}
+
+fn frame_len(version: Version, payload_size: usize) -> usize {
+    let header_size = match version {
+        Version::V1 => HEADER_SIZE_V1,
+        Version::V2 => HEADER_SIZE_V2,
+    };
+
+    1 + header_size + payload_size + CKSUM_SIZE
 }
 
 impl Default for Packet {
@@ -29,107 +147,1097 @@ impl Default for Packet {
         Self {
             header: Default::default(),
             message: Message::HEARTBEAT(Default::default()),
+            version: Version::V2,
+        }
+    }
+}
+
+/// The MAVLink version that [`PacketCodec`] frames outgoing packets with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeVersion {
+    /// Always use MAVLink 1 framing.
+    ///
+    /// Messages with ids that do not fit into a single byte can not be framed
+    /// with MAVLink 1, these are sent with MAVLink 2 framing instead.
+    V1,
+
+    /// Always use MAVLink 2 framing.
+    #[default]
+    V2,
+
+    /// Use the version of the most recently decoded frame, MAVLink 2 is used
+    /// until the first frame is decoded.
+    MatchPeer,
+}
+
+/// Encodes and decodes MAVLink 1 and MAVLink 2 frames.
+///
+/// Both versions are decoded on the same stream, and the version of each
+/// frame is recorded in [`Packet::version`]. Clones of a codec share the
+/// version of the peer, so a codec that is cloned into the read and write
+/// halves of a connection can still match the version of the peer.
+///
+/// When signing is enabled, every outgoing packet is signed (and therefore
+/// framed with MAVLink 2), incoming signed packets are verified, and incoming
+/// unsigned packets are subject to the [`UnsignedPolicy`].
+///
+/// The codec is generic over the dialect `M`, [`PacketCodec::new`] constructs
+/// a codec for the default dialect, and [`PacketCodec::for_dialect`] for any
+/// other dialect.
+///
+/// [`UnsignedPolicy`]: crate::signing::UnsignedPolicy
+#[derive(Debug)]
+pub struct PacketCodec<M = Message> {
+    version: EncodeVersion,
+    peer_is_v1: Arc<AtomicBool>,
+    signing: Option<Signing>,
+    synced: bool,
+    stats: Arc<Counters>,
+    crc_extras: HashMap<MessageId, u8>,
+    pass_unknown: bool,
+    keep_raw: bool,
+    dialect: PhantomData<fn() -> M>,
+}
+
+impl PacketCodec {
+    pub fn new(version: EncodeVersion) -> Self {
+        Self::for_dialect(version)
+    }
+}
+
+impl Default for PacketCodec {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<M> Clone for PacketCodec<M> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            peer_is_v1: self.peer_is_v1.clone(),
+            signing: self.signing.clone(),
+            synced: self.synced,
+            stats: self.stats.clone(),
+            crc_extras: self.crc_extras.clone(),
+            pass_unknown: self.pass_unknown,
+            keep_raw: self.keep_raw,
+            dialect: PhantomData,
         }
     }
 }
 
-pub struct PacketCodec;
+impl<M: MessageExt> PacketCodec<M> {
+    /// Constructs a codec for the dialect `M`.
+    pub fn for_dialect(version: EncodeVersion) -> Self {
+        Self {
+            version,
+            peer_is_v1: Default::default(),
+            signing: None,
+            synced: false,
+            stats: Default::default(),
+            crc_extras: Default::default(),
+            pass_unknown: false,
+            keep_raw: false,
+            dialect: PhantomData,
+        }
+    }
+
+    /// Advances the buffer, the next call has to search for a magic byte.
+    fn advance(&mut self, src: &mut BytesMut, count: usize) {
+        src.advance(count);
+        self.synced = false;
+    }
+
+    /// Enables MAVLink 2 signing.
+    pub fn with_signing(mut self, signing: Signing) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    /// Registers the CRC extra of a message that is not in the dialect.
+    ///
+    /// Frames that carry this message are validated, and decoded into
+    /// [`RawPacket`]s.
+    pub fn with_crc_extra(mut self, message_id: MessageId, crc_extra: u8) -> Self {
+        self.crc_extras.insert(message_id, crc_extra);
+        self
+    }
 
-impl Encoder<Packet> for PacketCodec {
+    /// Decodes frames with unknown message ids into [`RawPacket`]s, even if
+    /// their CRC extras are not registered.
+    ///
+    /// The checksum of such frames can not be validated, so a noisy stream
+    /// may produce bogus raw packets.
+    pub fn pass_unknown(mut self) -> Self {
+        self.pass_unknown = true;
+        self
+    }
+
+    /// Decodes every valid frame into a [`DecodeEvent::Raw`], including the
+    /// frames of messages that are in the dialect, so that they can be
+    /// forwarded byte-for-byte with their signatures.
+    ///
+    /// The [`Decoder`] of the codec still yields decoded packets, only its
+    /// [`events`](PacketCodec::events) are affected.
+    pub fn keep_raw(mut self) -> Self {
+        self.keep_raw = true;
+        self
+    }
+
+    /// Returns the CRC extra of the given message id, if it is known.
+    fn crc_extra(&self, message_id: MessageId) -> Option<u8> {
+        match M::extra_crc(message_id) {
+            // Messages that are not in the dialect have a CRC extra of zero.
+            0 if M::default_message_from_id(message_id).is_err() => {
+                self.crc_extras.get(&message_id).copied()
+            }
+            crc_extra => Some(crc_extra),
+        }
+    }
+
+    /// Returns the version an outgoing packet with the given message id will
+    /// be framed with.
+    fn outgoing_version(&self, message_id: u32) -> Version {
+        let version = match self.version {
+            // MAVLink 1 frames can not be signed.
+            _ if self.signing.is_some() => Version::V2,
+            EncodeVersion::V1 => Version::V1,
+            EncodeVersion::V2 => Version::V2,
+            EncodeVersion::MatchPeer if self.peer_is_v1.load(Ordering::Relaxed) => Version::V1,
+            EncodeVersion::MatchPeer => Version::V2,
+        };
+
+        // MAVLink 1 only has a single byte for the message id.
+        if message_id > u8::MAX as u32 {
+            Version::V2
+        } else {
+            version
+        }
+    }
+}
+
+fn crc(data: &[u8], extra: u8) -> u16 {
+    let mut state = State::<MCRF4XX>::new();
+    state.update(data);
+    state.update(&[extra]);
+    state.get()
+}
+
+impl<M: MessageExt> Encoder<Packet<M>> for PacketCodec<M> {
     type Error = Error;
 
     fn encode(
         &mut self,
-        packet: Packet,
+        packet: Packet<M>,
         dst: &mut BytesMut,
     ) -> std::result::Result<(), Self::Error> {
-        let mut raw = RawPacket::new();
-        raw.serialize_message(packet.header, &packet.message);
-        dst.extend_from_slice(raw.raw_bytes());
+        let Packet { header, message, .. } = packet;
+        let message_id = message.message_id();
+        let version = self.outgoing_version(message_id);
+
+        let mut payload = [0; MAX_PAYLOAD_SIZE];
+        let payload_size = message.ser(version, &mut payload);
+        let payload = &payload[..payload_size];
+
+        let start = dst.len();
+
+        match version {
+            Version::V1 => {
+                dst.extend_from_slice(&[
+                    MAGIC_BYTE_V1,
+                    payload_size as u8,
+                    header.sequence,
+                    header.system_id,
+                    header.component_id,
+                    message_id as u8,
+                ]);
+            }
+            Version::V2 => {
+                let [id0, id1, id2, _] = message_id.to_le_bytes();
+                let incompatibility_flags = match self.signing {
+                    Some(_) => IFLAG_SIGNED,
+                    None => 0,
+                };
+
+                dst.extend_from_slice(&[
+                    MAGIC_BYTE_V2,
+                    payload_size as u8,
+                    incompatibility_flags,
+                    0, // compatibility flags
+                    header.sequence,
+                    header.system_id,
+                    header.component_id,
+                    id0,
+                    id1,
+                    id2,
+                ]);
+            }
+        }
+
+        dst.extend_from_slice(payload);
+
+        // The checksum covers everything but the magic byte.
+        let checksum = crc(&dst[(start + 1)..], M::extra_crc(message_id));
+        dst.extend_from_slice(&checksum.to_le_bytes());
+
+        // The signature covers the whole frame, including the checksum.
+        if let Some(signing) = &self.signing {
+            let signature = signing.sign(&dst[start..]);
+            dst.extend_from_slice(&signature);
+        }
+
         Ok(())
     }
 }
 
-impl Decoder for PacketCodec {
-    type Item = Packet;
+impl<M> Encoder<RawPacket> for PacketCodec<M> {
     type Error = Error;
 
-    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // TODO: Do not search for the magic byte every time this function gets
-        // called. Store this information in a boolean, and check that boolean
-        // until we advance the buffer. After we advance, clear the boolean.
-        //
-        // Find the position of the magic byte.
-        let magic_byte_position = src
-            .iter()
-            .position(|n| *n == MAGIC_BYTE)
-            .unwrap_or(src.len());
+    fn encode(&mut self, packet: RawPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let RawPacket { header, message_id, payload, .. } = &packet;
+        let start = dst.len();
+
+        match packet.version {
+            Version::V1 => {
+                dst.extend_from_slice(&[
+                    MAGIC_BYTE_V1,
+                    payload.len() as u8,
+                    header.sequence,
+                    header.system_id,
+                    header.component_id,
+                    *message_id as u8,
+                ]);
+            }
+            Version::V2 => {
+                let [id0, id1, id2, _] = message_id.to_le_bytes();
+
+                dst.extend_from_slice(&[
+                    MAGIC_BYTE_V2,
+                    payload.len() as u8,
+                    packet.incompatibility_flags,
+                    packet.compatibility_flags,
+                    header.sequence,
+                    header.system_id,
+                    header.component_id,
+                    id0,
+                    id1,
+                    id2,
+                ]);
+            }
+        }
+
+        dst.extend_from_slice(payload);
 
-        // Advance the buf, so that the first byte is *always* the magic byte.
-        src.advance(magic_byte_position);
+        // Recalculate the checksum if we can, the header might have changed.
+        let checksum = match packet.crc_extra {
+            Some(crc_extra) => crc(&dst[(start + 1)..], crc_extra),
+            None => packet.checksum,
+        };
+
+        dst.extend_from_slice(&checksum.to_le_bytes());
+
+        // Forward the signature as it is, we can not sign on behalf of others.
+        if let Some(signature) = &packet.signature {
+            dst.extend_from_slice(signature);
+        }
+
+        Ok(())
+    }
+}
+
+impl<M: MessageExt> Encoder<Frame<M>> for PacketCodec<M> {
+    type Error = Error;
+
+    fn encode(&mut self, frame: Frame<M>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match frame {
+            Frame::Packet(packet) => self.encode(packet, dst),
+            Frame::Raw(packet) => self.encode(packet, dst),
+        }
+    }
+}
+
+impl<M: MessageExt> PacketCodec<M> {
+    /// Decodes the next frame in the buffer into an event.
+    ///
+    /// Returns `None` if the buffer does not contain a complete frame.
+    fn decode_event(&mut self, src: &mut BytesMut) -> Option<DecodeEvent<M>> {
+        // Search for a magic byte only if we have advanced since the last call,
+        // otherwise the first byte is still the magic byte we have found.
+        if !self.synced {
+            // Find the position of the magic byte of either version.
+            let magic_byte_position = src
+                .iter()
+                .position(|n| *n == MAGIC_BYTE_V1 || *n == MAGIC_BYTE_V2)
+                .unwrap_or(src.len());
+
+            // Advance the buf, so that the first byte is *always* a magic byte.
+            src.advance(magic_byte_position);
+            self.synced = !src.is_empty();
+        }
+
+        // Ensure that we have the magic byte and the payload size.
+        if src.len() < 2 {
+            return None;
+        }
+
+        let (version, header_size) = match src[0] {
+            MAGIC_BYTE_V1 => (Version::V1, HEADER_SIZE_V1),
+            _ => (Version::V2, HEADER_SIZE_V2),
+        };
 
         // Ensure that we have *at least* the header of the packet.
-        if src.len() < Packet::HEADER_SIZE {
-            return Ok(None);
+        if src.len() < 1 + header_size {
+            return None;
         }
 
         // Extract payload size from header.
         let payload_size = src[1] as usize;
 
-        // Determine signature size using incompatibility flags.
-        let signature_size = 13 * (src[2] & Packet::IFLAG_SIGNED) as usize;
+        // Determine signature size using incompatibility flags, MAVLink 1
+        // frames are never signed.
+        let signature_size = match version {
+            Version::V1 => 0,
+            Version::V2 => Signing::SIGNATURE_SIZE * (src[2] & IFLAG_SIGNED) as usize,
+        };
 
         // Calculate the total packet size.
-        let packet_size =
-            1 + Packet::HEADER_SIZE + payload_size + Packet::CKSUM_SIZE + signature_size;
+        let packet_size = 1 + header_size + payload_size + CKSUM_SIZE + signature_size;
 
         // Ensure that we have the required amount of bytes to read packet.
         if src.len() < packet_size {
-            return Ok(None);
+            return None;
         }
 
-        // Calculate CRC and validate the packet.
-        let message_id = u32::from_le_bytes([src[7], src[8], src[9], 0]);
-        let crc_extra = Message::extra_crc(message_id);
-        let crc_data = &src[1..(1 + Packet::HEADER_SIZE + payload_size)];
-
-        let mut state = State::<MCRF4XX>::new();
-        state.update(crc_data);
-        state.update(&[crc_extra]);
+        // Extract the header fields, their positions depend on the version.
+        let (header, message_id) = match version {
+            Version::V1 => (
+                Header { sequence: src[2], system_id: src[3], component_id: src[4] },
+                src[5] as u32,
+            ),
+            Version::V2 => (
+                Header { sequence: src[4], system_id: src[5], component_id: src[6] },
+                u32::from_le_bytes([src[7], src[8], src[9], 0]),
+            ),
+        };
 
-        let crc = state.get();
-        let offset = 1 + Packet::HEADER_SIZE + payload_size;
+        // Calculate CRC and validate the packet. The CRC of unknown messages can
+        // not be calculated, these are only accepted if we pass them through.
+        let offset = 1 + header_size + payload_size;
+        let crc_extra = self.crc_extra(message_id);
         let checksum = u16::from_le_bytes([src[offset], src[offset + 1]]);
+        let valid = match crc_extra {
+            Some(crc_extra) => crc(&src[1..offset], crc_extra) == checksum,
+            None => self.pass_unknown,
+        };
 
         // Validate CRC.
-        if crc == checksum {
-            let payload_begin = 1 + Packet::HEADER_SIZE;
-            let payload_end = payload_begin + payload_size;
-            let payload = &src[payload_begin..payload_end];
-
-            let message = Message::parse(V2, message_id, payload)
-                .map_err(|_| Error::new(InvalidData, "Invalid message."))?;
-
-            let packet = Packet {
-                message,
-                header: Header {
-                    sequence: src[4],
-                    system_id: src[5],
-                    component_id: src[6],
-                },
+        if !valid {
+            // The magic byte might be noise, and its "packet" might contain the
+            // beginning of a valid packet. Skip only the magic byte, and rescan.
+            self.advance(src, 1);
+
+            return Some(self.stats.record(DecodeEvent::BadCrc { header, message_id }));
+        }
+
+        // Validate the signature, or whether we accept the packet unsigned.
+        if let Some(signing) = &self.signing {
+            let accepted = match signature_size {
+                0 => signing.accepts_unsigned(message_id),
+                _ => {
+                    let (frame, signature) = src[..packet_size].split_at(offset + 2);
+                    signing.verify(frame, signature, header)
+                }
             };
 
-            // Clear the current packet.
-            src.advance(packet_size);
+            if !accepted {
+                // Clear the current packet.
+                self.advance(src, packet_size);
 
-            // Return valid packet.
-            Ok(Some(packet))
-        } else {
-            // Clear the current packet.
-            src.advance(packet_size);
+                return Some(self.stats.record(DecodeEvent::BadSignature { header, message_id }));
+            }
+        }
+
+        let payload = &src[(1 + header_size)..offset];
+        let message = M::parse(version, message_id, payload);
+
+        let raw = || RawPacket {
+            header,
+            version,
+            message_id,
+            incompatibility_flags: if version == Version::V2 { src[2] } else { 0 },
+            compatibility_flags: if version == Version::V2 { src[3] } else { 0 },
+            payload: payload.to_vec(),
+            checksum,
+            signature: src[(offset + 2)..packet_size].try_into().ok(),
+            crc_extra,
+        };
+
+        let event = match message {
+            Ok(message) => {
+                // Remember the version of the peer, so that we can match it.
+                self.peer_is_v1.store(version == Version::V1, Ordering::Relaxed);
+
+                match self.keep_raw {
+                    true => DecodeEvent::Raw(raw()),
+                    false => DecodeEvent::Packet(Packet { header, message, version }),
+                }
+            }
+            Err(ParserError::UnknownMessage { .. }) => DecodeEvent::UnknownMessage(raw()),
+            Err(_) => DecodeEvent::InvalidMessage { header, message_id },
+        };
+
+        // Clear the current packet, the CRC guarantees that it is a packet.
+        self.advance(src, packet_size);
+
+        Some(self.stats.record(event))
+    }
+
+    /// Decodes the next frame in the buffer into an event, at the end of the
+    /// stream.
+    fn decode_event_eof(&mut self, src: &mut BytesMut) -> Option<DecodeEvent<M>> {
+        match self.decode_event(src) {
+            None if src.is_empty() => None,
+            // The remaining bytes will never complete the packet. Skip the
+            // magic byte, since there might be other packets after it.
+            None => {
+                let len = src.len();
+                self.advance(src, 1);
+                Some(self.stats.record(DecodeEvent::Truncated { len }))
+            }
+            event => event,
+        }
+    }
+
+    /// Returns a snapshot of the decoder counters.
+    ///
+    /// The counters are shared between the clones of a codec.
+    pub fn stats(&self) -> DecodeStats {
+        self.stats.snapshot()
+    }
+
+    /// Turns the codec into a codec that decodes [`DecodeEvent`]s.
+    pub fn events(self) -> EventCodec<M> {
+        EventCodec(self)
+    }
+}
+
+impl<M: MessageExt> Decoder for PacketCodec<M> {
+    type Item = Packet<M>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode_event(src) {
+            Some(DecodeEvent::Packet(packet)) => Ok(Some(packet)),
+            Some(DecodeEvent::Raw(packet)) => Ok(packet.decode()),
+            Some(event) => Err(event.into()),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.decode_event_eof(src) {
+                Some(DecodeEvent::Packet(packet)) => break Ok(Some(packet)),
+                Some(DecodeEvent::Raw(packet)) => break Ok(packet.decode()),
+                // The stream has ended anyway, so look for more packets.
+                Some(DecodeEvent::Truncated { .. }) => {}
+                Some(event) => break Err(event.into()),
+                None => break Ok(None),
+            }
+        }
+    }
+}
+
+/// The outcome of decoding a single frame.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum DecodeEvent<M = Message> {
+    /// A valid packet.
+    Packet(Packet<M>),
+
+    /// A valid packet that is kept undecoded, see [`PacketCodec::keep_raw`].
+    Raw(RawPacket),
+
+    /// A frame whose checksum does not match, the header might be noise.
+    BadCrc { header: Header, message_id: MessageId },
+
+    /// A valid frame that carries a message that is not in the dialect.
+    UnknownMessage(RawPacket),
+
+    /// A valid frame whose payload could not be parsed into its message.
+    InvalidMessage { header: Header, message_id: MessageId },
+
+    /// A frame with an invalid signature, or an unsigned frame that is
+    /// rejected by the [`UnsignedPolicy`](crate::signing::UnsignedPolicy).
+    BadSignature { header: Header, message_id: MessageId },
+
+    /// The stream ended in the middle of a frame, `len` bytes were left.
+    Truncated { len: usize },
+}
+
+impl<M> DecodeEvent<M> {
+    /// Returns the packet if the event is a valid packet.
+    pub fn packet(self) -> Option<Packet<M>> {
+        match self {
+            DecodeEvent::Packet(packet) => Some(packet),
+            _ => None,
+        }
+    }
+}
+
+impl<M> DecodeEvent<M> {
+    /// Returns the frame if the event is a valid packet, or a raw packet.
+    pub fn frame(self) -> Option<Frame<M>> {
+        match self {
+            DecodeEvent::Packet(packet) => Some(Frame::Packet(packet)),
+            DecodeEvent::Raw(packet) | DecodeEvent::UnknownMessage(packet) => Some(Frame::Raw(packet)),
+            _ => None,
+        }
+    }
+}
+
+impl<M> From<Packet<M>> for DecodeEvent<M> {
+    fn from(packet: Packet<M>) -> Self {
+        DecodeEvent::Packet(packet)
+    }
+}
 
-            // Return invalid CRC error.
-            Err(Error::new(InvalidData, "Invalid CRC."))
+impl<M> From<Frame<M>> for DecodeEvent<M> {
+    fn from(frame: Frame<M>) -> Self {
+        match frame {
+            Frame::Packet(packet) => DecodeEvent::Packet(packet),
+            Frame::Raw(packet) => DecodeEvent::UnknownMessage(packet),
         }
     }
 }
+
+impl<M> From<DecodeEvent<M>> for Error {
+    fn from(event: DecodeEvent<M>) -> Self {
+        let reason = match event {
+            DecodeEvent::Packet(_) | DecodeEvent::Raw(_) => "Valid packet.",
+            DecodeEvent::BadCrc { .. } => "Invalid CRC.",
+            DecodeEvent::UnknownMessage(_) => "Unknown message.",
+            DecodeEvent::InvalidMessage { .. } => "Invalid message.",
+            DecodeEvent::BadSignature { .. } => "Invalid signature.",
+            DecodeEvent::Truncated { .. } => "Truncated packet.",
+        };
+
+        Error::new(InvalidData, reason)
+    }
+}
+
+/// A [`PacketCodec`] that decodes every frame into a [`DecodeEvent`].
+///
+/// Unlike [`PacketCodec`], invalid frames do not produce errors, so they do
+/// not end a framed stream.
+#[derive(Debug)]
+pub struct EventCodec<M = Message>(PacketCodec<M>);
+
+impl<M> Clone for EventCodec<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl Default for EventCodec {
+    fn default() -> Self {
+        PacketCodec::default().events()
+    }
+}
+
+impl<M: MessageExt> EventCodec<M> {
+    /// Returns a snapshot of the decoder counters.
+    pub fn stats(&self) -> DecodeStats {
+        self.0.stats()
+    }
+}
+
+impl<M: MessageExt> Decoder for EventCodec<M> {
+    type Item = DecodeEvent<M>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.0.decode_event(src))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.0.decode_event_eof(src))
+    }
+}
+
+impl<M: MessageExt> Encoder<Packet<M>> for EventCodec<M> {
+    type Error = Error;
+
+    fn encode(&mut self, packet: Packet<M>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.0.encode(packet, dst)
+    }
+}
+
+impl<M> Encoder<RawPacket> for EventCodec<M> {
+    type Error = Error;
+
+    fn encode(&mut self, packet: RawPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.0.encode(packet, dst)
+    }
+}
+
+impl<M: MessageExt> Encoder<Frame<M>> for EventCodec<M> {
+    type Error = Error;
+
+    fn encode(&mut self, frame: Frame<M>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.0.encode(frame, dst)
+    }
+}
+
+/// Counters of the decoded events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    pub packets: u64,
+    pub bad_crc: u64,
+    pub unknown_message: u64,
+    pub invalid_message: u64,
+    pub bad_signature: u64,
+    pub truncated: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters([AtomicU64; 6]);
+
+impl Counters {
+    fn record<M>(&self, event: DecodeEvent<M>) -> DecodeEvent<M> {
+        let index = match event {
+            DecodeEvent::Packet(_) | DecodeEvent::Raw(_) => 0,
+            DecodeEvent::BadCrc { .. } => 1,
+            DecodeEvent::UnknownMessage(_) => 2,
+            DecodeEvent::InvalidMessage { .. } => 3,
+            DecodeEvent::BadSignature { .. } => 4,
+            DecodeEvent::Truncated { .. } => 5,
+        };
+
+        self.0[index].fetch_add(1, Ordering::Relaxed);
+        event
+    }
+
+    fn snapshot(&self) -> DecodeStats {
+        let [packets, bad_crc, unknown_message, invalid_message, bad_signature, truncated] =
+            self.0.each_ref().map(|counter| counter.load(Ordering::Relaxed));
+
+        DecodeStats {
+            packets,
+            bad_crc,
+            unknown_message,
+            invalid_message,
+            bad_signature,
+            truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dialect::PROTOCOL_VERSION_DATA, signing::UnsignedPolicy};
+
+    fn packet(message: Message, version: Version) -> Packet {
+        let header = Header { system_id: 1, component_id: 1, sequence: 42 };
+        Packet { header, message, version }
+    }
+
+    fn encode(codec: &mut PacketCodec, packet: Packet) -> BytesMut {
+        let mut buf = BytesMut::new();
+        codec.encode(packet, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn codec_decodes_mixed_versions_on_the_same_stream() {
+        let mut v1 = PacketCodec::new(EncodeVersion::V1);
+        let mut v2 = PacketCodec::new(EncodeVersion::V2);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&[0x00, 0x42]);
+        buf.extend(encode(&mut v1, packet(Message::HEARTBEAT(Default::default()), Version::V1)));
+        buf.extend(encode(&mut v2, packet(Message::PROTOCOL_VERSION(PROTOCOL_VERSION_DATA {
+            version: 200,
+            ..Default::default()
+        }), Version::V2)));
+
+        let mut codec = PacketCodec::default();
+
+        let first = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(first.version, Version::V1);
+        assert_eq!(first.header.sequence, 42);
+        assert!(matches!(first.message, Message::HEARTBEAT(_)));
+
+        let second = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(second.version, Version::V2);
+        assert!(matches!(second.message, Message::PROTOCOL_VERSION(data) if data.version == 200));
+
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn codec_matches_the_version_of_the_peer() {
+        let mut codec = PacketCodec::new(EncodeVersion::MatchPeer);
+        let message = Message::HEARTBEAT(Default::default());
+
+        let buf = encode(&mut codec, packet(message.clone(), Version::V2));
+        assert_eq!(buf[0], MAGIC_BYTE_V2);
+
+        // Receive a MAVLink 1 frame through a clone of the codec.
+        let mut incoming = encode(
+            &mut PacketCodec::new(EncodeVersion::V1),
+            packet(Message::HEARTBEAT(Default::default()), Version::V1),
+        );
+        codec.clone().decode(&mut incoming).unwrap().unwrap();
+
+        let buf = encode(&mut codec, packet(message, Version::V2));
+        assert_eq!(buf[0], MAGIC_BYTE_V1);
+    }
+
+    #[test]
+    fn codec_falls_back_to_v2_for_large_message_ids() {
+        let mut codec = PacketCodec::new(EncodeVersion::V1);
+        let message = Message::PROTOCOL_VERSION(Default::default());
+
+        let mut buf = encode(&mut codec, packet(message, Version::V1));
+        assert_eq!(buf[0], MAGIC_BYTE_V2);
+
+        let decoded = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(matches!(decoded.message, Message::PROTOCOL_VERSION(_)));
+    }
+
+    #[test]
+    fn codec_verifies_signed_packets() {
+        let signing = Signing::new([7; 32], 1);
+        let mut sender = PacketCodec::default().with_signing(signing.clone());
+        let mut receiver = PacketCodec::default().with_signing(Signing::new([7; 32], 2));
+
+        let mut buf = encode(&mut sender, packet(Message::HEARTBEAT(Default::default()), Version::V1));
+        assert_eq!(buf[0], MAGIC_BYTE_V2);
+        assert_eq!(buf[2] & IFLAG_SIGNED, IFLAG_SIGNED);
+
+        // Keep a copy of the frame to replay it later.
+        let replayed = buf.clone();
+
+        assert!(receiver.decode(&mut buf).unwrap().is_some());
+
+        let mut buf = replayed;
+        assert_eq!(receiver.decode(&mut buf).unwrap_err().kind(), InvalidData);
+        assert!(buf.is_empty());
+
+        // Packets signed with another key are rejected.
+        let mut forger = PacketCodec::default().with_signing(Signing::new([8; 32], 1));
+        let mut buf = encode(&mut forger, packet(Message::HEARTBEAT(Default::default()), Version::V2));
+        assert!(receiver.decode(&mut buf).is_err());
+    }
+
+    // `RADIO_STATUS` is not part of the minimal dialects.
+    #[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+    #[test]
+    fn codec_applies_the_unsigned_policy() {
+        let mut unsigned = PacketCodec::default();
+        let heartbeat = || packet(Message::HEARTBEAT(Default::default()), Version::V2);
+        let radio_status = packet(Message::RADIO_STATUS(Default::default()), Version::V2);
+
+        let mut strict = PacketCodec::default().with_signing(Signing::new([7; 32], 0));
+        assert!(strict.decode(&mut encode(&mut unsigned, heartbeat())).is_err());
+        assert!(strict.decode(&mut encode(&mut unsigned, radio_status)).unwrap().is_some());
+
+        let signing = Signing::new([7; 32], 0).unsigned(UnsignedPolicy::Accept);
+        let mut lenient = PacketCodec::default().with_signing(signing);
+        assert!(lenient.decode(&mut encode(&mut unsigned, heartbeat())).unwrap().is_some());
+
+        // Without signing, signed packets are accepted as they are.
+        let mut signer = PacketCodec::default().with_signing(Signing::new([7; 32], 0));
+        assert!(unsigned.decode(&mut encode(&mut signer, heartbeat())).unwrap().is_some());
+    }
+
+    /// A tiny xorshift generator, so that the noise is the same on every run.
+    struct Noise(u32);
+
+    impl Noise {
+        fn next(&mut self) -> u8 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as u8
+        }
+
+        fn bytes(&mut self, count: usize) -> Vec<u8> {
+            (0..count).map(|_| self.next()).collect()
+        }
+    }
+
+    /// Feeds the stream to a codec in chunks of the given size, and returns the
+    /// sequence numbers of the decoded packets.
+    fn decode_all(stream: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut codec = PacketCodec::default();
+        let mut buf = BytesMut::new();
+        let mut sequences = Vec::new();
+
+        for chunk in stream.chunks(chunk_size) {
+            buf.extend_from_slice(chunk);
+
+            loop {
+                match codec.decode(&mut buf) {
+                    Ok(Some(packet)) => sequences.push(packet.header.sequence),
+                    Ok(None) => break,
+                    Err(_) => {}
+                }
+            }
+        }
+
+        loop {
+            match codec.decode_eof(&mut buf) {
+                Ok(Some(packet)) => sequences.push(packet.header.sequence),
+                Ok(None) => break,
+                Err(_) => {}
+            }
+        }
+
+        sequences
+    }
+
+    /// Encodes a frame with the given sequence number, alternating versions.
+    fn frame(sequence: u8) -> BytesMut {
+        let version = if sequence % 2 == 0 { EncodeVersion::V2 } else { EncodeVersion::V1 };
+        let header = Header { system_id: 1, component_id: 1, sequence };
+        let message = Message::HEARTBEAT(Default::default());
+
+        encode(&mut PacketCodec::new(version), Packet { header, message, version: Version::V2 })
+    }
+
+    #[test]
+    fn codec_does_not_lose_packets_inside_bogus_frames() {
+        // Magic bytes of either version, claiming the maximum payload size,
+        // right in front of valid frames.
+        let corpus: [&[u8]; 4] = [
+            &[MAGIC_BYTE_V2, 0xFF],
+            &[MAGIC_BYTE_V1, 0xFF],
+            &[MAGIC_BYTE_V2, 0x10, 0x00, 0x00],
+            &[MAGIC_BYTE_V1, MAGIC_BYTE_V2, MAGIC_BYTE_V1],
+        ];
+
+        for garbage in corpus {
+            let mut stream = Vec::new();
+
+            for sequence in 0..8 {
+                stream.extend_from_slice(garbage);
+                stream.extend_from_slice(&frame(sequence));
+            }
+
+            for chunk_size in [1, 7, 64, stream.len()] {
+                assert_eq!(decode_all(&stream, chunk_size), (0..8).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn codec_does_not_lose_packets_in_noisy_streams() {
+        let mut noise = Noise(0x5eed);
+
+        for _ in 0..32 {
+            let mut stream = Vec::new();
+
+            for sequence in 0..32u8 {
+                let count = noise.next() as usize % 48;
+                let mut bytes = noise.bytes(count);
+
+                // Make sure that the noise contains magic bytes.
+                if let Some(byte) = bytes.first_mut() {
+                    *byte = if sequence % 2 == 0 { MAGIC_BYTE_V2 } else { MAGIC_BYTE_V1 };
+                }
+
+                stream.extend_from_slice(&bytes);
+                stream.extend_from_slice(&frame(sequence));
+            }
+
+            let chunk_size = 1 + noise.next() as usize;
+            assert_eq!(decode_all(&stream, chunk_size), (0..32).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn codec_skips_corrupted_frames() {
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&frame(0));
+
+        // Flip a payload bit of the second frame.
+        let mut corrupted = frame(1);
+        corrupted[8] ^= 0x01;
+        stream.extend_from_slice(&corrupted);
+
+        // Truncate the third frame.
+        let truncated = frame(2);
+        stream.extend_from_slice(&truncated[..truncated.len() - 3]);
+
+        stream.extend_from_slice(&frame(3));
+
+        assert_eq!(decode_all(&stream, 5), vec![0, 3]);
+    }
+
+    #[tokio::test]
+    async fn event_codec_does_not_end_the_stream() {
+        use futures_util::StreamExt;
+        use tokio_util::codec::FramedRead;
+
+        let mut corrupted = frame(1);
+        corrupted[8] ^= 0x01;
+
+        let mut signed = encode(
+            &mut PacketCodec::default().with_signing(Signing::new([1; 32], 0)),
+            packet(Message::HEARTBEAT(Default::default()), Version::V2),
+        );
+        let last = signed.len() - 1;
+        signed[last] ^= 0x01;
+
+        let truncated = frame(4);
+
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&frame(0));
+        stream.extend_from_slice(&corrupted[..corrupted.len() - 1]);
+        stream.extend_from_slice(&frame(2));
+        stream.extend_from_slice(&signed);
+        stream.extend_from_slice(&frame(3));
+        stream.extend_from_slice(&truncated[..truncated.len() - 1]);
+
+        let signing = Signing::new([1; 32], 1).unsigned(UnsignedPolicy::Accept);
+        let codec = PacketCodec::default().with_signing(signing).events();
+        let stats = codec.clone();
+
+        let events: Vec<_> = FramedRead::new(&stream[..], codec)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let sequences: Vec<_> = events
+            .iter()
+            .cloned()
+            .filter_map(DecodeEvent::packet)
+            .map(|packet| packet.header.sequence)
+            .collect();
+
+        assert_eq!(sequences, vec![0, 2, 3]);
+        assert!(events.iter().any(|e| matches!(e, DecodeEvent::BadSignature { .. })));
+        assert!(matches!(events.last(), Some(DecodeEvent::Truncated { .. })));
+
+        assert_eq!(events.len(), 6);
+        assert_eq!(stats.stats(), DecodeStats {
+            packets: 3,
+            bad_crc: 1,
+            bad_signature: 1,
+            truncated: 1,
+            ..Default::default()
+        });
+    }
+
+    /// Encodes a frame of a custom message that is not in the dialect.
+    fn custom_frame(crc_extra: u8) -> BytesMut {
+        let [id0, id1, id2, _] = 53_000u32.to_le_bytes();
+        let mut buf = BytesMut::from(&[MAGIC_BYTE_V2, 3, 0, 0, 7, 2, 3, id0, id1, id2, 1, 2, 3][..]);
+
+        let checksum = crc(&buf[1..], crc_extra);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn codec_passes_unknown_messages_through() {
+        let frame = custom_frame(0x55);
+
+        // Unknown messages can not be validated by default.
+        let mut codec = PacketCodec::default().events();
+        let event = codec.decode(&mut frame.clone()).unwrap();
+        assert!(matches!(event, Some(DecodeEvent::BadCrc { .. })));
+
+        // Registered messages are validated.
+        let mut codec = PacketCodec::default().with_crc_extra(53_000, 0x55).events();
+        let Some(DecodeEvent::UnknownMessage(raw)) = codec.decode(&mut frame.clone()).unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert_eq!(raw.message_id, 53_000);
+        assert_eq!(raw.payload, [1, 2, 3]);
+        assert_eq!(raw.crc_extra, Some(0x55));
+        assert_eq!(raw.header, Header { sequence: 7, system_id: 2, component_id: 3 });
+
+        let mut codec = PacketCodec::default().with_crc_extra(53_000, 0x56).events();
+        let event = codec.decode(&mut frame.clone()).unwrap();
+        assert!(matches!(event, Some(DecodeEvent::BadCrc { .. })));
+
+        // Unregistered messages can be passed through without validation.
+        let mut codec = PacketCodec::default().pass_unknown().events();
+        let Some(DecodeEvent::UnknownMessage(raw)) = codec.decode(&mut frame.clone()).unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert_eq!(raw.crc_extra, None);
+
+        // Raw packets are encoded byte-for-byte.
+        let mut buf = BytesMut::new();
+        codec.encode(raw, &mut buf).unwrap();
+        assert_eq!(buf, frame);
+    }
+
+    #[test]
+    fn codec_keeps_packets_raw() {
+        let mut signer = PacketCodec::default().with_signing(Signing::new([7; 32], 0));
+        let frame = encode(&mut signer, packet(Message::HEARTBEAT(Default::default()), Version::V2));
+
+        let mut codec = PacketCodec::default().keep_raw();
+        let Some(DecodeEvent::Raw(raw)) = codec.clone().events().decode(&mut frame.clone()).unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert!(raw.signature.is_some());
+        assert!(matches!(raw.decode::<Message>().unwrap().message, Message::HEARTBEAT(_)));
+
+        // Signed packets are forwarded as they are.
+        let mut buf = BytesMut::new();
+        codec.encode(raw, &mut buf).unwrap();
+        assert_eq!(buf, frame);
+
+        // The decoder still decodes packets.
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert_eq!(codec.stats().packets, 2);
+    }
+
+    #[test]
+    fn codec_reencodes_raw_packets_with_new_headers() {
+        let mut codec = PacketCodec::default().with_crc_extra(53_000, 0x55);
+        let mut events = codec.clone().events();
+
+        let Some(DecodeEvent::UnknownMessage(mut raw)) = events.decode(&mut custom_frame(0x55)).unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        raw.header.sequence = 8;
+
+        let mut buf = BytesMut::new();
+        codec.encode(raw, &mut buf).unwrap();
+
+        let Some(DecodeEvent::UnknownMessage(raw)) = events.decode(&mut buf).unwrap() else {
+            panic!("expected a raw packet");
+        };
+
+        assert_eq!(raw.header.sequence, 8);
+    }
+
+    #[cfg(feature = "common")]
+    #[test]
+    fn codec_decodes_other_dialects() {
+        use crate::dialects::common::{MavMessage, HEARTBEAT_DATA};
+
+        let mut codec = PacketCodec::<MavMessage>::for_dialect(EncodeVersion::V2);
+        let message = MavMessage::HEARTBEAT(HEARTBEAT_DATA { custom_mode: 7, ..Default::default() });
+        let header = Header { system_id: 1, component_id: 1, sequence: 0 };
+
+        let mut buf = BytesMut::new();
+        codec.encode(Packet { header, message, version: Version::V2 }, &mut buf).unwrap();
+
+        let packet = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(matches!(packet.message, MavMessage::HEARTBEAT(data) if data.custom_mode == 7));
+    }
+}
@@ -0,0 +1,310 @@
+//! Discovery of the systems and components on a link.
+//!
+//! [`Discovery`] watches the heartbeats that come through a link, and reports
+//! the systems that appear on it, or go silent, as [`DiscoveryEvent`]s.
+use crate::{
+    component::Component,
+    dialect::*,
+    link::{Link, LinkSender},
+    param::ParamEncoding,
+    wire::Packet,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_broadcast as broadcast;
+use futures_util::{future::{ready, Future}, stream, Stream, StreamExt};
+use futures_time::{
+    stream::interval,
+    time::Duration as FuturesTimeDuration,
+};
+
+/// A system is lost when none of its components sent a heartbeat for this
+/// long.
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A component that has sent a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComponentInfo {
+    pub component_id: u8,
+    pub mavtype: MavType,
+    pub autopilot: MavAutopilot,
+    pub last_heartbeat: Instant,
+}
+
+/// A system, and the components of it that have sent heartbeats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemInfo {
+    pub system_id: u8,
+    pub components: BTreeMap<u8, ComponentInfo>,
+    /// Whether a heartbeat of the system was received within the timeout.
+    pub connected: bool,
+    pub last_heartbeat: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryEvent {
+    /// A system sent its first heartbeat, or sent one again after it was lost.
+    SystemDiscovered(SystemInfo),
+
+    /// A connected system has a new component.
+    ComponentDiscovered { system_id: u8, component: ComponentInfo },
+
+    /// A system did not send a heartbeat within the timeout.
+    SystemLost(SystemInfo),
+}
+
+/// Discovers the systems on a link, see the [module documentation](self).
+///
+/// `Discovery` is a stream of the [`DiscoveryEvent`]s that happen after it
+/// was created, or cloned. Clones share the same systems.
+#[derive(Clone)]
+pub struct Discovery {
+    link: LinkSender,
+    systems: Arc<Mutex<Systems>>,
+    events: broadcast::Receiver<DiscoveryEvent>,
+}
+
+impl Discovery {
+    /// Constructs a new `Discovery` that loses systems after
+    /// [`HEARTBEAT_TIMEOUT`].
+    ///
+    /// This function returns a `Discovery` and a future. The future must be
+    /// spawned in order to discover systems, and it resolves when all of the
+    /// `Discovery` instances are dropped, or the link is closed.
+    pub fn new(link: Link) -> (Discovery, impl Future<Output = ()>) {
+        Self::with_timeout(link, HEARTBEAT_TIMEOUT)
+    }
+
+    /// Same as [`Discovery::new`], but loses systems after the given timeout.
+    pub fn with_timeout(link: Link, timeout: Duration) -> (Discovery, impl Future<Output = ()>) {
+        let (mut publisher, events) = broadcast::broadcast(64);
+        publisher.set_overflow(true);
+
+        let systems = Arc::new(Mutex::new(Systems::new(link.system_id(), timeout)));
+        let sender = link.sender();
+        let fut = discover(link, timeout, Arc::downgrade(&systems), publisher);
+
+        (Discovery { link: sender, systems, events }, fut)
+    }
+
+    /// Returns the systems that have been discovered, including the lost ones.
+    pub fn systems(&self) -> Vec<SystemInfo> {
+        self.systems.lock().unwrap().systems.values().cloned().collect()
+    }
+
+    pub fn system(&self, system_id: u8) -> Option<SystemInfo> {
+        self.systems.lock().unwrap().systems.get(&system_id).cloned()
+    }
+
+    /// Returns a `Component` for a component that has been discovered, which
+    /// encodes parameters as its autopilot does.
+    pub fn component(&self, system_id: u8, component_id: u8) -> Option<Component> {
+        let info = *self.system(system_id)?.components.get(&component_id)?;
+
+        let mut component = Component::new(component_id, system_id, self.link.link());
+        component.set_param_encoding(ParamEncoding::from_autopilot(info.autopilot));
+
+        Some(component)
+    }
+}
+
+impl Stream for Discovery {
+    type Item = DiscoveryEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().events).poll_next(cx)
+    }
+}
+
+enum Input {
+    Packet(Arc<Packet>),
+    Tick,
+    Closed,
+}
+
+async fn discover(
+    link: Link,
+    timeout: Duration,
+    systems: Weak<Mutex<Systems>>,
+    publisher: broadcast::Sender<DiscoveryEvent>,
+) {
+    // Check for lost systems a few times within the timeout.
+    let period = FuturesTimeDuration::from(timeout / 4);
+    let packets = link
+        .map(Input::Packet)
+        .chain(stream::once(ready(Input::Closed)));
+
+    let ticks = interval(period).map(|_| Input::Tick);
+    let mut inputs = stream::select(packets, ticks);
+
+    while let Some(input) = inputs.next().await {
+        // Stop when every `Discovery` is dropped.
+        let Some(systems) = systems.upgrade() else {
+            break;
+        };
+
+        let events = match input {
+            Input::Packet(packet) => match &packet.message {
+                Message::HEARTBEAT(heartbeat) => {
+                    systems.lock().unwrap().heartbeat(packet.header, heartbeat, Instant::now())
+                }
+                _ => continue,
+            },
+            Input::Tick => systems.lock().unwrap().expire(Instant::now()),
+            Input::Closed => break,
+        };
+
+        for event in events {
+            let _ = publisher.try_broadcast(event);
+        }
+    }
+}
+
+/// The systems that have been heard from.
+#[derive(Debug)]
+struct Systems {
+    own_system_id: u8,
+    timeout: Duration,
+    systems: HashMap<u8, SystemInfo>,
+}
+
+impl Systems {
+    fn new(own_system_id: u8, timeout: Duration) -> Self {
+        Self { own_system_id, timeout, systems: HashMap::new() }
+    }
+
+    fn heartbeat(&mut self, header: Header, heartbeat: &HEARTBEAT_DATA, now: Instant) -> Vec<DiscoveryEvent> {
+        let Header { system_id, component_id, .. } = header;
+
+        // Ignore our own heartbeats, which might be echoed back.
+        if system_id == self.own_system_id {
+            return Vec::new();
+        }
+
+        let component = ComponentInfo {
+            component_id,
+            mavtype: heartbeat.mavtype,
+            autopilot: heartbeat.autopilot,
+            last_heartbeat: now,
+        };
+
+        let system = self.systems.entry(system_id).or_insert_with(|| SystemInfo {
+            system_id,
+            components: BTreeMap::new(),
+            connected: false,
+            last_heartbeat: now,
+        });
+
+        let is_new_component = system.components.insert(component_id, component).is_none();
+        system.last_heartbeat = now;
+
+        if !system.connected {
+            system.connected = true;
+            vec![DiscoveryEvent::SystemDiscovered(system.clone())]
+        } else if is_new_component {
+            vec![DiscoveryEvent::ComponentDiscovered { system_id, component }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn expire(&mut self, now: Instant) -> Vec<DiscoveryEvent> {
+        self.systems
+            .values_mut()
+            .filter(|system| system.connected && now.duration_since(system.last_heartbeat) >= self.timeout)
+            .map(|system| {
+                system.connected = false;
+                DiscoveryEvent::SystemLost(system.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(system_id: u8, component_id: u8) -> Header {
+        Header { system_id, component_id, sequence: 0 }
+    }
+
+    fn heartbeat(mavtype: MavType) -> HEARTBEAT_DATA {
+        HEARTBEAT_DATA { mavtype, ..Default::default() }
+    }
+
+    #[test]
+    fn systems_are_discovered_and_lost() {
+        let mut systems = Systems::new(255, Duration::from_secs(3));
+        let now = Instant::now();
+        let later = |secs| now + Duration::from_secs(secs);
+
+        let events = systems.heartbeat(header(1, 1), &heartbeat(MavType::MAV_TYPE_QUADROTOR), now);
+        assert!(matches!(&events[..], [DiscoveryEvent::SystemDiscovered(system)] if system.connected));
+
+        let events = systems.heartbeat(header(1, 100), &heartbeat(MavType::MAV_TYPE_CAMERA), later(1));
+        assert!(matches!(
+            &events[..],
+            [DiscoveryEvent::ComponentDiscovered { system_id: 1, component }]
+                if component.mavtype == MavType::MAV_TYPE_CAMERA
+        ));
+
+        // Known components, and our own heartbeats are not reported.
+        assert!(systems.heartbeat(header(1, 1), &heartbeat(MavType::MAV_TYPE_QUADROTOR), later(2)).is_empty());
+        assert!(systems.heartbeat(header(255, 1), &heartbeat(MavType::MAV_TYPE_GCS), later(2)).is_empty());
+
+        assert!(systems.expire(later(4)).is_empty());
+
+        let events = systems.expire(later(5));
+        assert!(matches!(&events[..], [DiscoveryEvent::SystemLost(system)] if system.components.len() == 2));
+        assert!(systems.expire(later(6)).is_empty());
+        assert!(!systems.systems[&1].connected);
+
+        // The system comes back.
+        let events = systems.heartbeat(header(1, 1), &heartbeat(MavType::MAV_TYPE_QUADROTOR), later(7));
+        assert!(matches!(&events[..], [DiscoveryEvent::SystemDiscovered(_)]));
+    }
+
+    #[tokio::test]
+    async fn discovery_reports_systems_of_a_link() {
+        let autopilots = [(1, MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA), (2, MavAutopilot::MAV_AUTOPILOT_PX4)];
+        let packets = autopilots.map(|(system_id, autopilot)| Packet {
+            header: header(system_id, 1),
+            message: Message::HEARTBEAT(HEARTBEAT_DATA { autopilot, ..heartbeat(MavType::MAV_TYPE_QUADROTOR) }),
+            version: Version::V2,
+        });
+
+        let stream = futures::stream::iter(packets).chain(futures::stream::pending());
+        let (link, connection) = Link::new(futures::sink::drain(), stream, 255, 190);
+        let (mut discovery, discovering) = Discovery::with_timeout(link, Duration::from_millis(100));
+
+        tokio::spawn(connection);
+        tokio::spawn(discovering);
+
+        let mut discovered = Vec::new();
+        let mut lost = Vec::new();
+
+        while lost.len() < 2 {
+            match discovery.next().await.unwrap() {
+                DiscoveryEvent::SystemDiscovered(system) => discovered.push(system.system_id),
+                DiscoveryEvent::SystemLost(system) => lost.push(system.system_id),
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+
+        discovered.sort();
+        lost.sort();
+        assert_eq!((discovered, lost), (vec![1, 2], vec![1, 2]));
+
+        assert!(discovery.component(1, 2).is_none());
+        assert_eq!(discovery.component(1, 1).unwrap().param_encoding(), ParamEncoding::CCast);
+        assert_eq!(discovery.component(2, 1).unwrap().param_encoding(), ParamEncoding::Bytewise);
+        assert!(discovery.systems().iter().all(|system| !system.connected));
+    }
+}
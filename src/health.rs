@@ -0,0 +1,216 @@
+//! The health of a component, as reported by `SYS_STATUS` and
+//! `HOME_POSITION`.
+//!
+//! [`HealthTracker`] keeps the health that a component reported, so that it
+//! can be read or waited for at any time.
+use crate::{
+    component::Component,
+    dialect::*,
+    error::{Error, Result},
+};
+
+use std::time::Duration;
+
+use futures_util::{future::{ready, Future}, pin_mut, stream, Stream, StreamExt};
+use futures_time::{
+    stream::StreamExt as FuturesTimeStreamExt,
+    time::Duration as FuturesTimeDuration,
+};
+use tokio::sync::watch;
+
+use MavSysStatusSensor as Sensor;
+
+/// The health of a component, which is ready to fly when all of it is ok.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Health {
+    pub is_gyrometer_calibration_ok: bool,
+    pub is_accelerometer_calibration_ok: bool,
+    pub is_magnetometer_calibration_ok: bool,
+    pub is_local_position_ok: bool,
+    pub is_global_position_ok: bool,
+    /// Whether a home position has been received.
+    pub is_home_position_ok: bool,
+    /// Whether the pre-arm checks pass.
+    pub is_armable: bool,
+}
+
+impl Health {
+    pub fn is_all_ok(&self) -> bool {
+        self.is_gyrometer_calibration_ok
+            && self.is_accelerometer_calibration_ok
+            && self.is_magnetometer_calibration_ok
+            && self.is_local_position_ok
+            && self.is_global_position_ok
+            && self.is_home_position_ok
+            && self.is_armable
+    }
+
+    /// Updates the health with the given message, and returns whether it has
+    /// changed.
+    pub fn update(&mut self, message: &Message) -> bool {
+        let previous = *self;
+
+        match message {
+            Message::SYS_STATUS(status) => {
+                let present = status.onboard_control_sensors_present;
+                let health = status.onboard_control_sensors_health;
+                let is_ok = |sensor| is_present_enabled_healthy(status, sensor);
+
+                self.is_gyrometer_calibration_ok = present.contains(Sensor::MAV_SYS_STATUS_SENSOR_3D_GYRO);
+                self.is_accelerometer_calibration_ok = health.contains(Sensor::MAV_SYS_STATUS_SENSOR_3D_ACCEL);
+                self.is_magnetometer_calibration_ok = present.contains(Sensor::MAV_SYS_STATUS_SENSOR_3D_MAG);
+                self.is_global_position_ok = is_ok(Sensor::MAV_SYS_STATUS_SENSOR_GPS);
+                self.is_local_position_ok = is_ok(Sensor::MAV_SYS_STATUS_SENSOR_OPTICAL_FLOW)
+                    || is_ok(Sensor::MAV_SYS_STATUS_SENSOR_VISION_POSITION);
+                self.is_armable = health.contains(Sensor::MAV_SYS_STATUS_PREARM_CHECK);
+            }
+            Message::HOME_POSITION(_) => self.is_home_position_ok = true,
+            _ => {}
+        }
+
+        *self != previous
+    }
+}
+
+/// Tracks the health of a component, see the [module
+/// documentation](self).
+#[derive(Debug, Clone)]
+pub struct HealthTracker {
+    health: watch::Receiver<Health>,
+}
+
+impl HealthTracker {
+    /// Constructs a new `HealthTracker` for the given component.
+    ///
+    /// This function returns a `HealthTracker` and a future. The future must
+    /// be spawned in order to update the health, and it resolves when all of
+    /// the `HealthTracker` instances are dropped, or the link is closed.
+    pub fn new(component: Component) -> (HealthTracker, impl Future<Output = ()>) {
+        let (sender, health) = watch::channel(Health::default());
+        let fut = track(component, sender);
+
+        (HealthTracker { health }, fut)
+    }
+
+    /// Returns the latest health of the component.
+    pub fn health(&self) -> Health {
+        *self.health.borrow()
+    }
+
+    /// Returns a stream that yields the current health, and then the health
+    /// every time that it changes.
+    pub fn changes(&self) -> impl Stream<Item = Health> {
+        let mut health = self.health.clone();
+
+        // Yield the current health first.
+        health.mark_changed();
+
+        stream::unfold(health, |mut health| async move {
+            health.changed().await.ok()?;
+            let current = *health.borrow_and_update();
+            Some((current, health))
+        })
+    }
+
+    /// Waits until all of the health checks of the component pass, which
+    /// returns at once if they already do.
+    ///
+    /// The home position is only ok after a `HOME_POSITION` is received, which
+    /// some autopilots only send when it is requested.
+    pub async fn wait_health_ok(&self, timeout: Duration) -> Result<Health> {
+        let fut = self
+            .changes()
+            .filter(|health| ready(health.is_all_ok()))
+            .timeout(FuturesTimeDuration::from(timeout));
+
+        pin_mut!(fut);
+
+        fut
+            .next()
+            .await
+            .ok_or(Error::Closed)?
+            .or(Err(Error::Timeout))
+    }
+}
+
+async fn track(mut component: Component, sender: watch::Sender<Health>) {
+    while let Some(packet) = component.next().await {
+        // Stop when every `HealthTracker` is dropped.
+        if sender.is_closed() {
+            break;
+        }
+
+        sender.send_if_modified(|health| health.update(&packet.message));
+    }
+}
+
+fn is_present_enabled_healthy(status: &SYS_STATUS_DATA, sensor: Sensor) -> bool {
+    status.onboard_control_sensors_present.contains(sensor)
+        && status.onboard_control_sensors_enabled.contains(sensor)
+        && status.onboard_control_sensors_health.contains(sensor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{link::Link, wire::Packet};
+
+    #[test]
+    fn health_follows_sys_status() {
+        let sensors = Sensor::MAV_SYS_STATUS_SENSOR_3D_GYRO
+            | Sensor::MAV_SYS_STATUS_SENSOR_3D_ACCEL
+            | Sensor::MAV_SYS_STATUS_SENSOR_3D_MAG
+            | Sensor::MAV_SYS_STATUS_SENSOR_GPS
+            | Sensor::MAV_SYS_STATUS_SENSOR_OPTICAL_FLOW;
+
+        let status = |health: Sensor| Message::SYS_STATUS(SYS_STATUS_DATA {
+            onboard_control_sensors_present: sensors,
+            onboard_control_sensors_enabled: sensors,
+            onboard_control_sensors_health: health,
+            ..Default::default()
+        });
+
+        let mut health = Health::default();
+
+        assert!(health.update(&status(sensors)));
+        assert!(health.is_global_position_ok && health.is_local_position_ok);
+        assert!(!health.is_armable && !health.is_home_position_ok);
+
+        assert!(!health.update(&status(sensors)));
+        assert!(health.update(&status(sensors | Sensor::MAV_SYS_STATUS_PREARM_CHECK)));
+        assert!(health.update(&Message::HOME_POSITION(Default::default())));
+        assert!(health.is_all_ok());
+
+        // A GPS that is present and enabled, but unhealthy.
+        assert!(health.update(&status(Sensor::MAV_SYS_STATUS_PREARM_CHECK)));
+        assert!(!health.is_global_position_ok && !health.is_all_ok());
+        assert!(!health.update(&Message::HEARTBEAT(Default::default())));
+    }
+
+    #[tokio::test]
+    async fn tracker_keeps_the_health() {
+        let header = Header { component_id: 1, system_id: 1, sequence: 0 };
+        let status = Message::SYS_STATUS(SYS_STATUS_DATA {
+            onboard_control_sensors_present: Sensor::all(),
+            onboard_control_sensors_enabled: Sensor::all(),
+            onboard_control_sensors_health: Sensor::all(),
+            ..Default::default()
+        });
+
+        let messages = [status, Message::HOME_POSITION(Default::default())];
+        let packets = messages.map(|message| Packet { header, message, version: Version::V2 });
+        let stream = futures::stream::iter(packets).chain(futures::stream::pending());
+        let (link, connection) = Link::new(futures::sink::drain(), stream, 255, 190);
+        let (tracker, tracking) = HealthTracker::new(Component::new(1, 1, link));
+
+        tokio::spawn(connection);
+        tokio::spawn(tracking);
+
+        let health = tracker.wait_health_ok(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(tracker.health(), health);
+
+        // The messages are not sent again, but the health is kept.
+        assert_eq!(tracker.wait_health_ok(Duration::from_millis(10)).await.unwrap(), health);
+        assert_eq!(Box::pin(tracker.changes()).next().await, Some(health));
+    }
+}
@@ -0,0 +1,174 @@
+//! Periodic heartbeats, which tell the other systems that we are alive.
+//!
+//! Autopilots trigger their GCS failsafe when they stop hearing heartbeats
+//! from the GCS, so a [`Heartbeat`] should run for as long as a link is used.
+use crate::{
+    dialect::*,
+    link::{Link, LinkSender},
+};
+
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use futures_util::future::Future;
+use futures_time::{
+    task::sleep,
+    time::Duration as FuturesTimeDuration,
+};
+
+/// The MAVLink version that heartbeats advertise.
+const MAVLINK_VERSION: u8 = 3;
+
+/// Sends heartbeats through a link with the ids of the link.
+///
+/// Clones share the same state, which can be changed while the heartbeats are
+/// being sent.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    data: Arc<Mutex<HEARTBEAT_DATA>>,
+}
+
+impl Heartbeat {
+    /// The period of heartbeats that is expected by most autopilots.
+    pub const PERIOD: Duration = Duration::from_secs(1);
+
+    /// Constructs a new `Heartbeat` that sends the heartbeats of an active
+    /// GCS through the link every `period`.
+    ///
+    /// This function returns a `Heartbeat` and a future. The future must be
+    /// spawned in order to send the heartbeats, and it resolves when all of
+    /// the `Heartbeat` instances are dropped, or the link is closed.
+    pub fn new(link: Link, period: Duration) -> (Heartbeat, impl Future<Output = ()>) {
+        Self::with_data(link, period, HEARTBEAT_DATA {
+            custom_mode: 0,
+            mavtype: MavType::MAV_TYPE_GCS,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+            base_mode: MavModeFlag::empty(),
+            system_status: MavState::MAV_STATE_ACTIVE,
+            mavlink_version: MAVLINK_VERSION,
+        })
+    }
+
+    /// Same as [`Heartbeat::new`], but starts with the given heartbeat.
+    pub fn with_data(
+        link: Link,
+        period: Duration,
+        data: HEARTBEAT_DATA,
+    ) -> (Heartbeat, impl Future<Output = ()>) {
+        // Keep only the sending half, a link that is not read from would hold
+        // up a connection with back pressure.
+        let data = Arc::new(Mutex::new(data));
+        let fut = beat(link.sender(), period, Arc::downgrade(&data));
+
+        (Heartbeat { data }, fut)
+    }
+
+    /// Returns the heartbeat that is being sent.
+    pub fn data(&self) -> HEARTBEAT_DATA {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// Changes the heartbeat that is being sent, the change is sent with the
+    /// next heartbeat.
+    pub fn update(&self, f: impl FnOnce(&mut HEARTBEAT_DATA)) {
+        f(&mut self.data.lock().unwrap())
+    }
+
+    pub fn set_type(&self, mavtype: MavType) {
+        self.update(|data| data.mavtype = mavtype)
+    }
+
+    pub fn set_autopilot(&self, autopilot: MavAutopilot) {
+        self.update(|data| data.autopilot = autopilot)
+    }
+
+    pub fn set_state(&self, system_status: MavState) {
+        self.update(|data| data.system_status = system_status)
+    }
+
+    pub fn set_mode(&self, base_mode: MavModeFlag, custom_mode: u32) {
+        self.update(|data| {
+            data.base_mode = base_mode;
+            data.custom_mode = custom_mode;
+        })
+    }
+}
+
+async fn beat(link: LinkSender, period: Duration, data: Weak<Mutex<HEARTBEAT_DATA>>) {
+    // Stop when every `Heartbeat` is dropped.
+    while let Some(data) = data.upgrade() {
+        let heartbeat = data.lock().unwrap().clone();
+        drop(data);
+
+        if link.send_message(Message::HEARTBEAT(heartbeat)).await.is_err() {
+            break;
+        }
+
+        sleep(FuturesTimeDuration::from(period)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{link::Overflow, wire::{Frame, Packet}};
+    use futures_util::{future, StreamExt};
+
+    #[tokio::test]
+    async fn heartbeat_sends_the_current_state() {
+        let (sender, receiver) = flume::unbounded();
+        let sink = sender.into_sink();
+        let (link, connection) = Link::new(sink, futures::stream::empty::<Frame>(), 255, 190);
+
+        tokio::spawn(connection);
+
+        let (heartbeat, beating) = Heartbeat::new(link, Duration::from_millis(10));
+        let mut packets = receiver.into_stream().filter_map(|frame: Frame| future::ready(frame.packet()));
+
+        tokio::spawn(beating);
+
+        let packet = packets.next().await.unwrap();
+        assert_eq!((packet.header.system_id, packet.header.component_id), (255, 190));
+        assert!(matches!(
+            packet.message,
+            Message::HEARTBEAT(HEARTBEAT_DATA { mavtype: MavType::MAV_TYPE_GCS, .. })
+        ));
+
+        heartbeat.set_state(MavState::MAV_STATE_CRITICAL);
+
+        let state = loop {
+            if let Message::HEARTBEAT(data) = &packets.next().await.unwrap().message {
+                if data.system_status != MavState::MAV_STATE_ACTIVE {
+                    break data.system_status;
+                }
+            }
+        };
+
+        assert_eq!(state, MavState::MAV_STATE_CRITICAL);
+
+        // The heartbeats stop with the last handle.
+        drop(heartbeat);
+        while packets.next().await.is_some() {}
+    }
+
+    #[tokio::test]
+    async fn heartbeat_does_not_hold_up_back_pressure() {
+        let packets = futures::stream::iter(0..5).map(|sequence| Packet {
+            header: Header { system_id: 1, component_id: 1, sequence },
+            ..Default::default()
+        });
+
+        let builder = Link::builder().incoming_capacity(2).overflow(Overflow::BackPressure);
+        let (mut link, connection) = builder.build(futures::sink::drain(), packets, 255, 190);
+        tokio::spawn(connection);
+
+        let (_heartbeat, beating) = Heartbeat::new(link.clone(), Duration::from_millis(10));
+        tokio::spawn(beating);
+
+        for sequence in 0..5 {
+            assert_eq!(link.recv().await.unwrap().header.sequence, sequence);
+        }
+    }
+}
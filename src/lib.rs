@@ -1,13 +1,79 @@
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
 pub mod component;
 pub mod core;
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+pub mod discovery;
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+pub mod health;
+pub mod heartbeat;
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
 pub mod mission;
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+pub mod param;
+#[cfg(any(feature = "ardupilotmega", feature = "common", feature = "development"))]
+pub mod telemetry;
 
+#[cfg(not(any(
+    feature = "ardupilotmega",
+    feature = "development",
+    feature = "common",
+    feature = "standard",
+    feature = "minimal",
+)))]
+compile_error!("at least one of the dialect features must be enabled");
+
+/// The default dialect, which is used unless the core types are given another.
+///
+/// This is the most complete dialect among the enabled dialect features, in
+/// the order of `ardupilotmega`, `development`, `common`, `standard` and
+/// `minimal`. The high-level modules, such as [`component`], require one of
+/// the first three.
 pub mod dialect {
-    pub use mavlink::{MavHeader as Header, Message as MessageExt, MessageData };
+    pub use mavlink::{MavHeader as Header, MavlinkVersion as Version, Message as MessageExt, MessageData};
 
+    #[cfg(feature = "ardupilotmega")]
     pub use mavlink::ardupilotmega::{MavMessage as Message, *};
 
+    #[cfg(all(feature = "development", not(feature = "ardupilotmega")))]
+    pub use mavlink::development::{MavMessage as Message, *};
+
+    #[cfg(all(feature = "common", not(any(feature = "ardupilotmega", feature = "development"))))]
+    pub use mavlink::common::{MavMessage as Message, *};
+
+    #[cfg(all(
+        feature = "standard",
+        not(any(feature = "ardupilotmega", feature = "development", feature = "common")),
+    ))]
+    pub use mavlink::standard::{MavMessage as Message, *};
+
+    #[cfg(all(
+        feature = "minimal",
+        not(any(feature = "ardupilotmega", feature = "development", feature = "common", feature = "standard")),
+    ))]
+    pub use mavlink::minimal::{MavMessage as Message, *};
+
     pub type MessageId = u32;
 }
 
+/// The dialects that are enabled by the dialect features.
+///
+/// Core types such as [`wire::Packet`] and [`link::Link`] can be used with any
+/// of these, or with any other type that implements [`dialect::MessageExt`].
+pub mod dialects {
+    #[cfg(feature = "ardupilotmega")]
+    pub use mavlink::ardupilotmega;
+
+    #[cfg(feature = "common")]
+    pub use mavlink::common;
+
+    #[cfg(feature = "minimal")]
+    pub use mavlink::minimal;
+
+    #[cfg(feature = "standard")]
+    pub use mavlink::standard;
+
+    #[cfg(feature = "development")]
+    pub use mavlink::development;
+}
+
 pub use core::*;
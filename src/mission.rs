@@ -1,35 +1,154 @@
-use crate::dialect::{
-    MavCmd, MavFrame, MISSION_ITEM_DATA as RawMissionItem,
-    MISSION_ITEM_INT_DATA as RawMissionItemInt,
+use crate::{
+    dialect::{
+        MavCmd, MavFrame, MavMissionResult, Message, MissionState, MISSION_ITEM_DATA as RawMissionItem,
+        MISSION_ITEM_INT_DATA as RawMissionItemInt,
+    },
+    error::Error,
+    link::LinkSender,
 };
 
-pub trait IntoMissionItem {
-    fn raw(&self) -> RawMissionItem;
+use std::{
+    fmt,
+    pin::Pin,
+    result::Result as StdResult,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
 
-    fn int(&self) -> RawMissionItemInt {
-        fn scale(f: f32) -> i32 {
-            (f * 1e7) as i32
+use futures_util::{future::BoxFuture, FutureExt, Stream, StreamExt};
+
+/// The errors of the mission protocol.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MissionError {
+    /// The link failed, or was closed.
+    Link(Error),
+    /// The component did not respond, even after the retries.
+    Timeout,
+    /// The component ended the transfer with this result.
+    Rejected(MavMissionResult),
+    /// The component requested an item that is not in the mission.
+    InvalidSequence(u16),
+    /// A mission can have at most `u16::MAX` items.
+    TooManyItems(usize),
+    /// The component sent an item that does not fit the mission type.
+    InvalidItem(u16),
+}
+
+impl std::error::Error for MissionError {}
+
+impl fmt::Display for MissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissionError::Link(err) => write!(f, "link error: {err}"),
+            MissionError::Timeout => f.write_str("the component did not respond"),
+            MissionError::Rejected(result) => write!(f, "the component rejected the transfer with {result:?}"),
+            MissionError::InvalidSequence(seq) => write!(f, "the component requested the invalid item {seq}"),
+            MissionError::TooManyItems(count) => write!(f, "{count} items do not fit in a mission"),
+            MissionError::InvalidItem(seq) => write!(f, "the item {seq} does not fit the mission type"),
         }
+    }
+}
 
-        let mut int: RawMissionItemInt = Default::default();
-        let raw = self.raw();
+impl From<Error> for MissionError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Timeout => MissionError::Timeout,
+            err => MissionError::Link(err),
+        }
+    }
+}
+
+/// The progress of an upload, which is reported every time an item is sent,
+/// or a response is waited for again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UploadProgress {
+    /// The number of distinct items that were sent.
+    pub sent: usize,
+    pub total: usize,
+    /// The number of items that were sent again, and of the responses that
+    /// timed out.
+    pub retries: usize,
+}
+
+/// An upload that is in progress, see
+/// [`Component::start_mission_upload`](crate::component::Component::start_mission_upload).
+///
+/// The upload is a stream of its progress, which ends when the upload ends.
+/// Dropping it before the end cancels the upload. If the component was sent
+/// the count of the items, it is also told with a `MISSION_ACK` of
+/// `MAV_MISSION_OPERATION_CANCELLED`, unless the outgoing queue of the link is
+/// full, in which case the component gives up on its own after a timeout.
+pub struct MissionUpload {
+    upload: Option<BoxFuture<'static, StdResult<(), MissionError>>>,
+    progress: flume::Receiver<UploadProgress>,
+    result: Option<StdResult<(), MissionError>>,
+    started: Arc<AtomicBool>,
+    link: LinkSender,
+    cancel: Message,
+}
 
-        int.param1 = raw.param1;
-        int.param2 = raw.param2;
-        int.param3 = raw.param3;
-        int.param4 = raw.param4;
-        int.x = scale(raw.x);
-        int.y = scale(raw.y);
-        int.z = raw.z;
-        int.seq = raw.seq;
-        int.command = raw.command;
-        int.target_system = raw.target_system;
-        int.target_component = raw.target_component;
-        int.frame = raw.frame;
-        int.current = raw.current;
-        int.autocontinue = raw.autocontinue;
+impl MissionUpload {
+    pub(crate) fn new(
+        upload: BoxFuture<'static, StdResult<(), MissionError>>,
+        progress: flume::Receiver<UploadProgress>,
+        started: Arc<AtomicBool>,
+        link: LinkSender,
+        cancel: Message,
+    ) -> Self {
+        Self { upload: Some(upload), progress, result: None, started, link, cancel }
+    }
 
-        int
+    /// Waits until the upload ends, and returns its result.
+    pub async fn finish(mut self) -> StdResult<(), MissionError> {
+        while self.next().await.is_some() {}
+        self.result.take().unwrap_or(Err(MissionError::Link(Error::Closed)))
+    }
+
+    /// Cancels the upload, which is the same as dropping it.
+    pub fn cancel(self) {}
+}
+
+impl Stream for MissionUpload {
+    type Item = UploadProgress;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // The progress is only reported while the upload is polled here.
+        if let Some(upload) = &mut this.upload {
+            if let Poll::Ready(result) = upload.poll_unpin(cx) {
+                this.upload = None;
+                this.result = Some(result);
+            }
+        }
+
+        match this.progress.try_recv() {
+            Ok(progress) => Poll::Ready(Some(progress)),
+            Err(_) if this.upload.is_none() => Poll::Ready(None),
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for MissionUpload {
+    fn drop(&mut self) {
+        // Dropping the cancel when the queue is full is better than blocking
+        // the drop.
+        if self.upload.is_some() && self.started.load(Ordering::Relaxed) {
+            let _ = self.link.try_send_message(self.cancel.clone());
+        }
+    }
+}
+
+pub trait IntoMissionItem {
+    fn raw(&self) -> RawMissionItem;
+
+    fn int(&self) -> RawMissionItemInt {
+        int_from_raw(self.raw())
     }
 
     fn with(&self, system: u8, component: u8, seq: u16) -> RawMissionItem {
@@ -51,11 +170,63 @@ pub trait IntoMissionItem {
     }
 }
 
+// An item that is owned by an upload, in both forms, as they are built from
+// the original item.
+pub(crate) struct UploadItem {
+    raw: RawMissionItem,
+    int: RawMissionItemInt,
+}
+
+impl UploadItem {
+    pub(crate) fn new<I: IntoMissionItem>(item: &I) -> Self {
+        Self { raw: item.raw(), int: item.int() }
+    }
+}
+
+impl IntoMissionItem for UploadItem {
+    fn raw(&self) -> RawMissionItem {
+        self.raw.clone()
+    }
+
+    fn int(&self) -> RawMissionItemInt {
+        self.int.clone()
+    }
+}
+
+fn int_from_raw(raw: RawMissionItem) -> RawMissionItemInt {
+    fn scale(f: f32) -> i32 {
+        (f as f64 * 1e7).round() as i32
+    }
+
+    RawMissionItemInt {
+        param1: raw.param1,
+        param2: raw.param2,
+        param3: raw.param3,
+        param4: raw.param4,
+        x: scale(raw.x),
+        y: scale(raw.y),
+        z: raw.z,
+        seq: raw.seq,
+        command: raw.command,
+        target_system: raw.target_system,
+        target_component: raw.target_component,
+        frame: raw.frame,
+        current: raw.current,
+        autocontinue: raw.autocontinue,
+        mission_type: raw.mission_type,
+    }
+}
+
+/// A mission item, the positions are a latitude and longitude in degrees, and
+/// an altitude above the home position.
+#[derive(Debug, Clone, PartialEq)]
 pub enum MissionItem {
-    Waypoint(f32, f32, f32),
-    Takeoff(f32, f32, f32),
+    Waypoint(f64, f64, f32),
+    Takeoff(f64, f64, f32),
     ReturnToLaunch,
     DoChangeSpeed,
+    /// Any other item, as it is sent to the component.
+    Other(RawMissionItemInt),
 }
 
 impl IntoMissionItem for MissionItem {
@@ -66,8 +237,8 @@ impl IntoMissionItem for MissionItem {
             Waypoint(lat, lon, alt) => RawMissionItem {
                 command: MavCmd::MAV_CMD_NAV_WAYPOINT,
                 param4: f32::NAN,
-                x: lat,
-                y: lon,
+                x: lat as f32,
+                y: lon as f32,
                 z: alt,
                 autocontinue: true as u8,
                 frame: MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
@@ -76,8 +247,8 @@ impl IntoMissionItem for MissionItem {
             Takeoff(lat, lon, alt) => RawMissionItem {
                 command: MavCmd::MAV_CMD_NAV_TAKEOFF,
                 param4: f32::NAN,
-                x: lat,
-                y: lon,
+                x: lat as f32,
+                y: lon as f32,
                 z: alt,
                 autocontinue: true as u8,
                 frame: MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
@@ -96,6 +267,385 @@ impl IntoMissionItem for MissionItem {
                 param3: -2.0,
                 ..Default::default()
             },
+            Other(ref int) => RawMissionItem {
+                param1: int.param1,
+                param2: int.param2,
+                param3: int.param3,
+                param4: int.param4,
+                x: (int.x as f64 / 1e7) as f32,
+                y: (int.y as f64 / 1e7) as f32,
+                z: int.z,
+                seq: int.seq,
+                command: int.command,
+                target_system: int.target_system,
+                target_component: int.target_component,
+                frame: int.frame,
+                current: int.current,
+                autocontinue: int.autocontinue,
+                mission_type: int.mission_type,
+            },
         }
     }
+
+    // The positions are scaled from `f64`, a `f32` is only exact to about a
+    // meter.
+    fn int(&self) -> RawMissionItemInt {
+        use MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT as Relative;
+
+        match *self {
+            MissionItem::Waypoint(lat, lon, alt) => RawMissionItemInt {
+                param4: f32::NAN,
+                ..item_int(MavCmd::MAV_CMD_NAV_WAYPOINT, 0.0, Coordinate::new(lat, lon), alt, Relative)
+            },
+            MissionItem::Takeoff(lat, lon, alt) => RawMissionItemInt {
+                param4: f32::NAN,
+                ..item_int(MavCmd::MAV_CMD_NAV_TAKEOFF, 0.0, Coordinate::new(lat, lon), alt, Relative)
+            },
+            MissionItem::Other(ref int) => int.clone(),
+            ref item => int_from_raw(item.raw()),
+        }
+    }
+}
+
+impl From<RawMissionItemInt> for MissionItem {
+    /// Converts a downloaded item, the items that carry more than the
+    /// variants are kept as `Other`.
+    fn from(item: RawMissionItemInt) -> Self {
+        use MavFrame::{MAV_FRAME_GLOBAL_RELATIVE_ALT, MAV_FRAME_GLOBAL_RELATIVE_ALT_INT};
+
+        let is_relative = matches!(item.frame, MAV_FRAME_GLOBAL_RELATIVE_ALT | MAV_FRAME_GLOBAL_RELATIVE_ALT_INT);
+        let is_plain = [item.param1, item.param2, item.param3, item.param4]
+            .iter()
+            .all(|param| *param == 0.0 || param.is_nan());
+
+        let Coordinate { latitude_deg: lat, longitude_deg: lon } = Coordinate::from_int(&item);
+        let alt = item.z;
+
+        match item.command {
+            MavCmd::MAV_CMD_NAV_WAYPOINT if is_relative && is_plain => MissionItem::Waypoint(lat, lon, alt),
+            MavCmd::MAV_CMD_NAV_TAKEOFF if is_relative && is_plain => MissionItem::Takeoff(lat, lon, alt),
+            MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH => MissionItem::ReturnToLaunch,
+            _ => MissionItem::Other(item),
+        }
+    }
+}
+
+/// An event of the mission that the vehicle is flying, see
+/// [`Component::mission_events`](crate::component::Component::mission_events).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissionEvent {
+    /// The vehicle is heading to the item, `total` is the number of items if
+    /// the autopilot reports it.
+    Current { seq: u16, total: Option<u16> },
+
+    /// The vehicle reached the item.
+    Reached(u16),
+
+    /// The vehicle completed the mission.
+    Completed,
+}
+
+/// Turns `MISSION_CURRENT` and `MISSION_ITEM_REACHED` into mission events,
+/// `MISSION_CURRENT` is repeated periodically, so only its changes are
+/// reported.
+#[derive(Debug, Clone, Default)]
+pub struct MissionTracker {
+    current: Option<u16>,
+    total: Option<u16>,
+    completed: bool,
+}
+
+impl MissionTracker {
+    pub fn update(&mut self, message: &Message) -> Vec<MissionEvent> {
+        let mut events = Vec::new();
+
+        match message {
+            Message::MISSION_CURRENT(current) => {
+                // 0 is not supported, and `u16::MAX` is no mission.
+                self.total = Some(current.total).filter(|&total| !matches!(total, 0 | u16::MAX));
+
+                if self.current != Some(current.seq) {
+                    self.current = Some(current.seq);
+                    self.completed = false;
+                    events.push(MissionEvent::Current { seq: current.seq, total: self.total });
+                }
+
+                if current.mission_state == MissionState::MISSION_STATE_COMPLETE {
+                    self.complete(&mut events);
+                }
+            }
+            Message::MISSION_ITEM_REACHED(reached) => {
+                events.push(MissionEvent::Reached(reached.seq));
+
+                // The total does not count the home position of ArduPilot,
+                // so the last item has the sequence of the total.
+                if self.total == Some(reached.seq) {
+                    self.complete(&mut events);
+                }
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    fn complete(&mut self, events: &mut Vec<MissionEvent>) {
+        if !std::mem::replace(&mut self.completed, true) {
+            events.push(MissionEvent::Completed);
+        }
+    }
+}
+
+/// A position on the globe, which is sent with the precision of the integer
+/// mission items.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Coordinate {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+}
+
+impl Coordinate {
+    pub fn new(latitude_deg: f64, longitude_deg: f64) -> Self {
+        Self { latitude_deg, longitude_deg }
+    }
+
+    fn from_int(item: &RawMissionItemInt) -> Self {
+        Self::new(item.x as f64 / 1e7, item.y as f64 / 1e7)
+    }
+}
+
+/// A point that the vehicle might return to instead of the home position,
+/// which is uploaded as a `MAV_MISSION_TYPE_RALLY` item.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RallyPoint {
+    pub coordinate: Coordinate,
+    /// The altitude above the home position.
+    pub altitude_m: f32,
+}
+
+impl IntoMissionItem for RallyPoint {
+    fn raw(&self) -> RawMissionItem {
+        let int = self.int();
+
+        RawMissionItem {
+            command: int.command,
+            x: self.coordinate.latitude_deg as f32,
+            y: self.coordinate.longitude_deg as f32,
+            z: int.z,
+            frame: int.frame,
+            ..Default::default()
+        }
+    }
+
+    fn int(&self) -> RawMissionItemInt {
+        item_int(MavCmd::MAV_CMD_NAV_RALLY_POINT, 0.0, self.coordinate, self.altitude_m, MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT)
+    }
+}
+
+impl TryFrom<&RawMissionItemInt> for RallyPoint {
+    type Error = MissionError;
+
+    fn try_from(item: &RawMissionItemInt) -> StdResult<Self, Self::Error> {
+        match item.command {
+            MavCmd::MAV_CMD_NAV_RALLY_POINT => Ok(Self { coordinate: Coordinate::from_int(item), altitude_m: item.z }),
+            _ => Err(MissionError::InvalidItem(item.seq)),
+        }
+    }
+}
+
+/// An area of a geofence, that the vehicle must stay in when it is an
+/// inclusion, or out of when it is an exclusion.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenceZone {
+    Polygon { vertices: Vec<Coordinate>, inclusion: bool },
+    Circle { center: Coordinate, radius_m: f32, inclusion: bool },
+}
+
+/// A geofence, which is uploaded as `MAV_MISSION_TYPE_FENCE` items.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Geofence {
+    pub zones: Vec<FenceZone>,
+    /// The point, and the altitude that the vehicle returns to when it
+    /// breaches the fence.
+    pub return_point: Option<(Coordinate, f32)>,
+}
+
+impl Geofence {
+    /// Returns the items of the geofence, which have one item for every vertex
+    /// of the polygons.
+    pub fn items(&self) -> Vec<MissionItem> {
+        use MavCmd::*;
+
+        let frame = MavFrame::MAV_FRAME_GLOBAL;
+        let mut items = Vec::new();
+
+        if let Some((coordinate, altitude_m)) = self.return_point {
+            items.push(item_int(MAV_CMD_NAV_FENCE_RETURN_POINT, 0.0, coordinate, altitude_m, frame));
+        }
+
+        for zone in &self.zones {
+            match zone {
+                FenceZone::Polygon { vertices, inclusion } => {
+                    let command = match inclusion {
+                        true => MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION,
+                        false => MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION,
+                    };
+
+                    // Every vertex carries the number of vertices.
+                    let count = vertices.len() as f32;
+                    items.extend(vertices.iter().map(|&vertex| item_int(command, count, vertex, 0.0, frame)));
+                }
+                &FenceZone::Circle { center, radius_m, inclusion } => {
+                    let command = match inclusion {
+                        true => MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION,
+                        false => MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION,
+                    };
+
+                    items.push(item_int(command, radius_m, center, 0.0, frame));
+                }
+            }
+        }
+
+        items.into_iter().map(MissionItem::Other).collect()
+    }
+
+    /// Builds a geofence from downloaded items.
+    pub fn from_items(items: &[RawMissionItemInt]) -> StdResult<Self, MissionError> {
+        use MavCmd::*;
+
+        let mut fence = Geofence::default();
+        let mut rest = items;
+
+        while let Some(item) = rest.first() {
+            let inclusion = matches!(
+                item.command,
+                MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION | MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION
+            );
+
+            let len = match item.command {
+                MAV_CMD_NAV_FENCE_RETURN_POINT => {
+                    fence.return_point = Some((Coordinate::from_int(item), item.z));
+                    1
+                }
+                MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION | MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION => {
+                    let count = item.param1 as usize;
+
+                    let vertices = rest
+                        .get(..count)
+                        .filter(|vertices| count >= 3 && vertices.iter().all(|vertex| vertex.command == item.command))
+                        .ok_or(MissionError::InvalidItem(item.seq))?;
+
+                    let vertices = vertices.iter().map(Coordinate::from_int).collect();
+                    fence.zones.push(FenceZone::Polygon { vertices, inclusion });
+                    count
+                }
+                MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION | MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION => {
+                    let center = Coordinate::from_int(item);
+                    fence.zones.push(FenceZone::Circle { center, radius_m: item.param1, inclusion });
+                    1
+                }
+                _ => return Err(MissionError::InvalidItem(item.seq)),
+            };
+
+            rest = &rest[len..];
+        }
+
+        Ok(fence)
+    }
+}
+
+fn item_int(command: MavCmd, param1: f32, coordinate: Coordinate, z: f32, frame: MavFrame) -> RawMissionItemInt {
+    RawMissionItemInt {
+        command,
+        param1,
+        x: (coordinate.latitude_deg * 1e7).round() as i32,
+        y: (coordinate.longitude_deg * 1e7).round() as i32,
+        z,
+        frame,
+        autocontinue: true as u8,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{MISSION_CURRENT_DATA, MISSION_ITEM_REACHED_DATA};
+
+    #[test]
+    fn tracker_reports_mission_events() {
+        let current = |seq, mission_state| Message::MISSION_CURRENT(MISSION_CURRENT_DATA {
+            seq,
+            total: 2,
+            mission_state,
+            ..Default::default()
+        });
+
+        let reached = |seq| Message::MISSION_ITEM_REACHED(MISSION_ITEM_REACHED_DATA { seq });
+
+        let mut tracker = MissionTracker::default();
+        let active = MissionState::MISSION_STATE_ACTIVE;
+
+        assert_eq!(tracker.update(&current(1, active)), [MissionEvent::Current { seq: 1, total: Some(2) }]);
+        assert!(tracker.update(&current(1, active)).is_empty());
+        assert_eq!(tracker.update(&reached(1)), [MissionEvent::Reached(1)]);
+        assert_eq!(tracker.update(&current(2, active)).len(), 1);
+        assert_eq!(tracker.update(&reached(2)), [MissionEvent::Reached(2), MissionEvent::Completed]);
+
+        // The completion is only reported once.
+        assert!(tracker.update(&current(2, MissionState::MISSION_STATE_COMPLETE)).is_empty());
+    }
+
+    #[test]
+    fn items_keep_the_precision_of_integer_items() {
+        let waypoint = MissionItem::Waypoint(38.3706171, 27.2008103, 5.0);
+        let int = waypoint.int();
+
+        assert_eq!((int.x, int.y), (383706171, 272008103));
+        assert_eq!(MissionItem::from(int.clone()), waypoint);
+
+        // The items that are sent as `MISSION_ITEM` are as close as a `f32`
+        // can be.
+        let position = |raw: RawMissionItem| (raw.x, raw.y);
+        let closest = (38.3706171f64 as f32, 27.2008103f64 as f32);
+
+        assert_eq!(position(waypoint.raw()), closest);
+        assert_eq!(position(UploadItem::new(&waypoint).raw()), closest);
+        assert_eq!(position(MissionItem::Other(int).raw()), closest);
+
+        // Items without an integer form are rounded, not truncated.
+        let raw = RawMissionItem { x: 27.2, ..Default::default() };
+        assert_eq!(int_from_raw(raw).x, 272000008);
+    }
+
+    #[test]
+    fn geofences_are_converted_to_items_and_back() {
+        let vertices = vec![
+            Coordinate::new(38.3706171, 27.2008103),
+            Coordinate::new(38.3705263, 27.2010598),
+            Coordinate::new(38.3706665, 27.2011341),
+        ];
+
+        let fence = Geofence {
+            zones: vec![
+                FenceZone::Polygon { vertices, inclusion: true },
+                FenceZone::Circle { center: Coordinate::new(38.3706, 27.2009), radius_m: 5.0, inclusion: false },
+            ],
+            return_point: Some((Coordinate::new(38.37, 27.2), 10.0)),
+        };
+
+        let items: Vec<_> = fence.items().iter().map(IntoMissionItem::int).collect();
+
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[1].command, MavCmd::MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION);
+        assert_eq!((items[3].param1, items[1].x), (3.0, 383706171));
+        assert_eq!(Geofence::from_items(&items).unwrap(), fence);
+
+        // A polygon that is missing a vertex.
+        assert!(matches!(Geofence::from_items(&items[..3]), Err(MissionError::InvalidItem(_))));
+
+        let rally = RallyPoint { coordinate: Coordinate::new(38.37, 27.2), altitude_m: 30.0 };
+        assert_eq!(RallyPoint::try_from(&rally.int()).unwrap(), rally);
+        assert!(RallyPoint::try_from(&items[0]).is_err());
+    }
 }
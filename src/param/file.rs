@@ -0,0 +1,304 @@
+//! Parameter files, as saved by Mission Planner and QGroundControl.
+use super::{Param, ParamValue};
+use crate::dialect::MavParamType;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamFormat {
+    /// The `.param` files of Mission Planner, with a `NAME,VALUE` line for
+    /// each parameter. The values do not carry a type, so they are read as
+    /// [`FileValue::Untyped`].
+    MissionPlanner,
+
+    /// The `.params` files of QGroundControl, with a tab separated line of
+    /// system id, component id, name, value and type for each parameter.
+    QGroundControl,
+}
+
+impl ParamFormat {
+    /// Returns the format of a file by its extension, which is `.params` for
+    /// QGroundControl, and anything else for Mission Planner.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension() {
+            Some(extension) if extension == "params" => ParamFormat::QGroundControl,
+            _ => ParamFormat::MissionPlanner,
+        }
+    }
+}
+
+/// The value of a parameter in a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileValue {
+    /// A value of a Mission Planner file, which gets the type of the
+    /// parameter when it is applied.
+    Untyped(f64),
+
+    /// A value of a QGroundControl file, or of a component.
+    Typed(ParamValue),
+}
+
+impl FileValue {
+    /// Returns the value as a number, which is exact for every type.
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            FileValue::Untyped(value) => *value,
+            FileValue::Typed(value) => value.as_f64(),
+        }
+    }
+
+    /// Converts the value to the given type, or returns `None` if it does
+    /// not fit exactly, see [`ParamValue::from_f64_exact`].
+    pub fn to_type(&self, param_type: MavParamType) -> Option<ParamValue> {
+        match self {
+            FileValue::Typed(value) if value.param_type() == param_type => Some(*value),
+            value => ParamValue::from_f64_exact(value.as_f64(), param_type),
+        }
+    }
+
+    // Untyped values are written as integers if they are whole, and fit.
+    fn param_type(&self) -> MavParamType {
+        use MavParamType::*;
+
+        match *self {
+            FileValue::Typed(value) => value.param_type(),
+            FileValue::Untyped(value) if ParamValue::from_f64_exact(value, MAV_PARAM_TYPE_INT32).is_some() => {
+                MAV_PARAM_TYPE_INT32
+            }
+            FileValue::Untyped(_) => MAV_PARAM_TYPE_REAL32,
+        }
+    }
+}
+
+impl From<ParamValue> for FileValue {
+    fn from(value: ParamValue) -> Self {
+        FileValue::Typed(value)
+    }
+}
+
+/// A set of parameter values, ordered by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParamFile {
+    pub params: BTreeMap<String, FileValue>,
+}
+
+/// A parameter whose value in a file differs from the one of a component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamDiff {
+    pub name: String,
+    pub file: FileValue,
+    /// The value of the component, or `None` if it has no such parameter.
+    pub live: Option<ParamValue>,
+}
+
+impl ParamFile {
+    /// Loads a file, whose format is chosen by [`ParamFormat::from_path`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let format = ParamFormat::from_path(&path);
+        Self::parse(&fs::read_to_string(path)?, format)
+    }
+
+    /// Saves the file, whose format is chosen by [`ParamFormat::from_path`].
+    ///
+    /// QGroundControl files carry the ids of the component, which are written
+    /// as 1 and 1.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let format = ParamFormat::from_path(&path);
+        fs::write(path, self.format(format, 1, 1))
+    }
+
+    pub fn parse(text: &str, format: ParamFormat) -> Result<Self> {
+        let mut params = BTreeMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason| Error::new(ErrorKind::InvalidData, format!("line {}: {reason}", number + 1));
+
+            let (name, value) = match format {
+                // Mission Planner also reads values that are separated by
+                // spaces or tabs.
+                ParamFormat::MissionPlanner => {
+                    let mut fields = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|field| !field.is_empty());
+
+                    let (Some(name), Some(value)) = (fields.next(), fields.next()) else {
+                        return Err(invalid("expected a name and a value"));
+                    };
+
+                    let value = value.parse().map_err(|_| invalid("invalid value"))?;
+                    (name, FileValue::Untyped(value))
+                }
+                ParamFormat::QGroundControl => {
+                    let fields: Vec<_> = line.split_whitespace().collect();
+
+                    let [_, _, name, value, param_type] = fields[..] else {
+                        return Err(invalid("expected 5 fields"));
+                    };
+
+                    let value = value.parse().map_err(|_| invalid("invalid value"))?;
+                    let param_type = param_type
+                        .parse()
+                        .ok()
+                        .and_then(param_type_from_u8)
+                        .ok_or_else(|| invalid("unsupported type"))?;
+
+                    let value = ParamValue::from_f64_exact(value, param_type)
+                        .ok_or_else(|| invalid("value does not fit the type"))?;
+
+                    (name, FileValue::Typed(value))
+                }
+            };
+
+            params.insert(name.to_string(), value);
+        }
+
+        Ok(Self { params })
+    }
+
+    /// Formats the file, QGroundControl files carry the given ids.
+    pub fn format(&self, format: ParamFormat, system_id: u8, component_id: u8) -> String {
+        let mut text = String::new();
+
+        match format {
+            ParamFormat::MissionPlanner => {
+                for (name, value) in &self.params {
+                    let _ = writeln!(text, "{name},{}", number(value));
+                }
+            }
+            ParamFormat::QGroundControl => {
+                text.push_str("# Vehicle-Id Component-Id Name Value Type\n");
+
+                for (name, value) in &self.params {
+                    let param_type = value.param_type() as u8;
+                    let _ = writeln!(text, "{system_id}\t{component_id}\t{name}\t{}\t{param_type}", number(value));
+                }
+            }
+        }
+
+        text
+    }
+
+    /// Returns the parameters of the file that are missing from, or differ
+    /// from the given parameters of a component.
+    pub fn diff<'a>(&self, live: impl IntoIterator<Item = &'a Param>) -> Vec<ParamDiff> {
+        let live: BTreeMap<_, _> = live.into_iter().map(|param| (param.name.as_str(), param.value)).collect();
+
+        self.params
+            .iter()
+            .filter_map(|(name, &file)| {
+                let live = live.get(name.as_str()).copied();
+
+                // The values are compared in the type of the component, a
+                // value that does not fit it is always different.
+                let changed = live.is_none_or(|live| file.to_type(live.param_type()) != Some(live));
+                changed.then(|| ParamDiff { name: name.clone(), file, live })
+            })
+            .collect()
+    }
+}
+
+impl<'a> FromIterator<&'a Param> for ParamFile {
+    fn from_iter<I: IntoIterator<Item = &'a Param>>(params: I) -> Self {
+        let params = params.into_iter().map(|param| (param.name.clone(), param.value.into())).collect();
+        Self { params }
+    }
+}
+
+// Floats are written with the shortest text that reads back the same.
+fn number(value: &FileValue) -> String {
+    match value {
+        FileValue::Typed(ParamValue::Real32(value)) => value.to_string(),
+        value => value.as_f64().to_string(),
+    }
+}
+
+fn param_type_from_u8(param_type: u8) -> Option<MavParamType> {
+    use MavParamType::*;
+
+    [
+        MAV_PARAM_TYPE_UINT8,
+        MAV_PARAM_TYPE_INT8,
+        MAV_PARAM_TYPE_UINT16,
+        MAV_PARAM_TYPE_INT16,
+        MAV_PARAM_TYPE_UINT32,
+        MAV_PARAM_TYPE_INT32,
+        MAV_PARAM_TYPE_REAL32,
+    ]
+    .into_iter()
+    .find(|&supported| supported as u8 == param_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_parsed_and_formatted() {
+        let mission_planner = "#NOTE: 1/1/2024\nSERIAL0_BAUD,115\nSTAT_FLTTIME,16777217\nWPNAV_SPEED 0.1\n\n";
+        let file = ParamFile::parse(mission_planner, ParamFormat::MissionPlanner).unwrap();
+
+        assert_eq!(file.params["SERIAL0_BAUD"], FileValue::Untyped(115.0));
+        assert_eq!(file.params["WPNAV_SPEED"], FileValue::Untyped(0.1));
+
+        // Integers above 2^24 are kept exactly.
+        let flight_time = file.params["STAT_FLTTIME"].to_type(MavParamType::MAV_PARAM_TYPE_INT32);
+        assert_eq!(flight_time, Some(ParamValue::Int32(16777217)));
+
+        let text = file.format(ParamFormat::MissionPlanner, 1, 1);
+        assert_eq!(text, "SERIAL0_BAUD,115\nSTAT_FLTTIME,16777217\nWPNAV_SPEED,0.1\n");
+
+        let qgroundcontrol = "# Onboard parameters for Vehicle 1\n1\t1\tCOM_RC_LOSS_T\t0.5\t9\n1\t1\tSYS_AUTOSTART\t4001\t6\n";
+        let file = ParamFile::parse(qgroundcontrol, ParamFormat::QGroundControl).unwrap();
+
+        assert_eq!(file.params["SYS_AUTOSTART"], FileValue::Typed(ParamValue::Int32(4001)));
+
+        let text = file.format(ParamFormat::QGroundControl, 1, 1);
+        assert_eq!(ParamFile::parse(&text, ParamFormat::QGroundControl).unwrap(), file);
+
+        let error = ParamFile::parse("# header\nNAME", ParamFormat::MissionPlanner).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected a name and a value");
+        assert!(ParamFile::parse("1\t1\tNAME\t1\t8", ParamFormat::QGroundControl).is_err());
+
+        let error = ParamFile::parse("1\t1\tNAME\t256\t1", ParamFormat::QGroundControl).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: value does not fit the type");
+    }
+
+    #[test]
+    fn files_are_diffed_by_value() {
+        let text = "RATE,5\nSPEED,7.5\nNEW,1\nRATIO,0.1\nMODE,2.5\n";
+        let file = ParamFile::parse(text, ParamFormat::MissionPlanner).unwrap();
+        let param = |name: &str, value| Param { name: name.to_string(), value, index: 0, count: 2 };
+        let live = [
+            param("RATE", ParamValue::Uint8(5)),
+            param("SPEED", ParamValue::Real32(5.0)),
+            param("RATIO", ParamValue::Real32(0.1)),
+            param("MODE", ParamValue::Uint8(2)),
+        ];
+
+        let diff = file.diff(&live);
+
+        // A fraction does not fit an integer, so it is always different.
+        assert_eq!(diff, [
+            ParamDiff { name: "MODE".to_string(), file: FileValue::Untyped(2.5), live: Some(ParamValue::Uint8(2)) },
+            ParamDiff { name: "NEW".to_string(), file: FileValue::Untyped(1.0), live: None },
+            ParamDiff {
+                name: "SPEED".to_string(),
+                file: FileValue::Untyped(7.5),
+                live: Some(ParamValue::Real32(5.0)),
+            },
+        ]);
+
+        assert_eq!(ParamFormat::from_path("copter.params"), ParamFormat::QGroundControl);
+        assert_eq!(ParamFormat::from_path("copter.param"), ParamFormat::MissionPlanner);
+    }
+}
@@ -0,0 +1,606 @@
+//! The parameters of a component.
+//!
+//! Parameters are named values that configure a component. They are carried
+//! in the float field of the parameter messages, see [`ParamEncoding`] for how
+//! integers fit in there. [`Parameters`] keeps a cache of the parameters of a
+//! component, and reports the values that change.
+//!
+//! Parameter files can be read and written with [`ParamFile`], and applied
+//! with [`Parameters::apply`].
+pub mod file;
+
+pub use file::{FileValue, ParamDiff, ParamFile, ParamFormat};
+
+use crate::{
+    component::{Component, ComponentSender},
+    dialect::*,
+    error::{Error, Result},
+    wire::Packet,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use async_broadcast as broadcast;
+use futures_util::{future::Future, Stream, StreamExt};
+
+/// The maximum length of a parameter name.
+pub const PARAM_NAME_LEN: usize = 16;
+
+/// How integer parameters are carried in the float field of the messages.
+///
+/// Components that do not tell their encoding are assumed to cast, as most
+/// autopilots other than PX4 do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamEncoding {
+    /// The bytes of the integer are copied into the float, as PX4 does.
+    Bytewise,
+
+    /// The integer is cast to a float, as ArduPilot does, which is only exact
+    /// up to 2^24.
+    #[default]
+    CCast,
+}
+
+impl ParamEncoding {
+    /// Returns the encoding of an autopilot, which is only bytewise for PX4.
+    pub fn from_autopilot(autopilot: MavAutopilot) -> Self {
+        match autopilot {
+            MavAutopilot::MAV_AUTOPILOT_PX4 => ParamEncoding::Bytewise,
+            _ => ParamEncoding::CCast,
+        }
+    }
+
+    /// Returns the encoding that the capabilities of an `AUTOPILOT_VERSION`
+    /// tell, if any.
+    pub fn from_capabilities(capabilities: MavProtocolCapability) -> Option<Self> {
+        if capabilities.contains(MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_BYTEWISE) {
+            Some(ParamEncoding::Bytewise)
+        } else if capabilities.contains(MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_C_CAST) {
+            Some(ParamEncoding::CCast)
+        } else {
+            None
+        }
+    }
+}
+
+/// The value of a parameter, with its type.
+///
+/// 64-bit parameters can not be carried in the float field, so they are not
+/// supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamValue {
+    Uint8(u8),
+    Int8(i8),
+    Uint16(u16),
+    Int16(i16),
+    Uint32(u32),
+    Int32(i32),
+    Real32(f32),
+}
+
+impl ParamValue {
+    /// Decodes the float field of a message, or returns `None` for a type
+    /// that is not supported.
+    pub fn decode(value: f32, param_type: MavParamType, encoding: ParamEncoding) -> Option<Self> {
+        use MavParamType::*;
+
+        if param_type == MAV_PARAM_TYPE_REAL32 {
+            return Some(ParamValue::Real32(value));
+        }
+
+        let value = match encoding {
+            ParamEncoding::Bytewise => {
+                let [b0, b1, b2, b3] = value.to_le_bytes();
+
+                match param_type {
+                    MAV_PARAM_TYPE_UINT8 => ParamValue::Uint8(b0),
+                    MAV_PARAM_TYPE_INT8 => ParamValue::Int8(b0 as i8),
+                    MAV_PARAM_TYPE_UINT16 => ParamValue::Uint16(u16::from_le_bytes([b0, b1])),
+                    MAV_PARAM_TYPE_INT16 => ParamValue::Int16(i16::from_le_bytes([b0, b1])),
+                    MAV_PARAM_TYPE_UINT32 => ParamValue::Uint32(u32::from_le_bytes([b0, b1, b2, b3])),
+                    MAV_PARAM_TYPE_INT32 => ParamValue::Int32(i32::from_le_bytes([b0, b1, b2, b3])),
+                    _ => return None,
+                }
+            }
+            ParamEncoding::CCast => return Self::from_f64(value as f64, param_type),
+        };
+
+        Some(value)
+    }
+
+    /// Encodes the value into the float field, and the type of a message.
+    pub fn encode(&self, encoding: ParamEncoding) -> (f32, MavParamType) {
+        use MavParamType::*;
+
+        let (bytes, param_type) = match *self {
+            ParamValue::Real32(value) => return (value, MAV_PARAM_TYPE_REAL32),
+            ParamValue::Uint8(value) => ([value, 0, 0, 0], MAV_PARAM_TYPE_UINT8),
+            ParamValue::Int8(value) => ([value as u8, 0, 0, 0], MAV_PARAM_TYPE_INT8),
+            ParamValue::Uint16(value) => {
+                let [b0, b1] = value.to_le_bytes();
+                ([b0, b1, 0, 0], MAV_PARAM_TYPE_UINT16)
+            }
+            ParamValue::Int16(value) => {
+                let [b0, b1] = value.to_le_bytes();
+                ([b0, b1, 0, 0], MAV_PARAM_TYPE_INT16)
+            }
+            ParamValue::Uint32(value) => (value.to_le_bytes(), MAV_PARAM_TYPE_UINT32),
+            ParamValue::Int32(value) => (value.to_le_bytes(), MAV_PARAM_TYPE_INT32),
+        };
+
+        match encoding {
+            ParamEncoding::Bytewise => (f32::from_le_bytes(bytes), param_type),
+            ParamEncoding::CCast => (self.as_f64() as f32, param_type),
+        }
+    }
+
+    /// Converts a number to a value of the given type, or returns `None` for
+    /// a type that is not supported. Integers are truncated and saturated.
+    pub fn from_f64(value: f64, param_type: MavParamType) -> Option<Self> {
+        use MavParamType::*;
+
+        let value = match param_type {
+            MAV_PARAM_TYPE_UINT8 => ParamValue::Uint8(value as u8),
+            MAV_PARAM_TYPE_INT8 => ParamValue::Int8(value as i8),
+            MAV_PARAM_TYPE_UINT16 => ParamValue::Uint16(value as u16),
+            MAV_PARAM_TYPE_INT16 => ParamValue::Int16(value as i16),
+            MAV_PARAM_TYPE_UINT32 => ParamValue::Uint32(value as u32),
+            MAV_PARAM_TYPE_INT32 => ParamValue::Int32(value as i32),
+            MAV_PARAM_TYPE_REAL32 => ParamValue::Real32(value as f32),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Converts a number to a value of the given type, or returns `None` if
+    /// it does not fit: integers must be whole and in range, and floats in
+    /// the range of `f32`, which they are rounded to.
+    pub fn from_f64_exact(value: f64, param_type: MavParamType) -> Option<Self> {
+        let converted = Self::from_f64(value, param_type)?;
+
+        let fits = match converted {
+            ParamValue::Real32(real) => real.is_finite() == value.is_finite(),
+            converted => converted.as_f64() == value,
+        };
+
+        fits.then_some(converted)
+    }
+
+    pub fn param_type(&self) -> MavParamType {
+        use MavParamType::*;
+
+        match self {
+            ParamValue::Uint8(_) => MAV_PARAM_TYPE_UINT8,
+            ParamValue::Int8(_) => MAV_PARAM_TYPE_INT8,
+            ParamValue::Uint16(_) => MAV_PARAM_TYPE_UINT16,
+            ParamValue::Int16(_) => MAV_PARAM_TYPE_INT16,
+            ParamValue::Uint32(_) => MAV_PARAM_TYPE_UINT32,
+            ParamValue::Int32(_) => MAV_PARAM_TYPE_INT32,
+            ParamValue::Real32(_) => MAV_PARAM_TYPE_REAL32,
+        }
+    }
+
+    /// Returns the value as a number, which is exact for every type.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            ParamValue::Uint8(value) => value as f64,
+            ParamValue::Int8(value) => value as f64,
+            ParamValue::Uint16(value) => value as f64,
+            ParamValue::Int16(value) => value as f64,
+            ParamValue::Uint32(value) => value as f64,
+            ParamValue::Int32(value) => value as f64,
+            ParamValue::Real32(value) => value as f64,
+        }
+    }
+}
+
+/// A parameter, as reported by a `PARAM_VALUE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub value: ParamValue,
+    pub index: u16,
+    /// The number of parameters of the component.
+    pub count: u16,
+}
+
+impl Param {
+    /// Decodes a `PARAM_VALUE`, or returns `None` for a type that is not
+    /// supported.
+    pub fn decode(data: &PARAM_VALUE_DATA, encoding: ParamEncoding) -> Option<Self> {
+        Some(Self {
+            name: param_name(&data.param_id),
+            value: ParamValue::decode(data.param_value, data.param_type, encoding)?,
+            index: data.param_index,
+            count: data.param_count,
+        })
+    }
+
+    pub(crate) fn from_packet(packet: &Packet, encoding: ParamEncoding) -> Option<Self> {
+        match &packet.message {
+            Message::PARAM_VALUE(data) => Self::decode(data, encoding),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the name field of the messages for the given name, which is
+/// truncated to [`PARAM_NAME_LEN`] bytes.
+pub fn param_id(name: &str) -> [u8; PARAM_NAME_LEN] {
+    let mut id = [0; PARAM_NAME_LEN];
+    let len = name.len().min(PARAM_NAME_LEN);
+
+    id[..len].copy_from_slice(&name.as_bytes()[..len]);
+    id
+}
+
+/// Returns the name in the name field of the messages, which is only null
+/// terminated when it is shorter than [`PARAM_NAME_LEN`] bytes.
+pub fn param_name(id: &[u8; PARAM_NAME_LEN]) -> String {
+    let len = id.iter().position(|&byte| byte == 0).unwrap_or(PARAM_NAME_LEN);
+    String::from_utf8_lossy(&id[..len]).into_owned()
+}
+
+/// The parameters of a component, see the [module documentation](self).
+///
+/// The cache is updated with every `PARAM_VALUE` of the component, including
+/// the ones that are sent when another GCS changes a parameter. Clones share
+/// the same cache.
+#[derive(Clone)]
+pub struct Parameters {
+    component: ComponentSender,
+    cache: Arc<Mutex<HashMap<String, Param>>>,
+    publisher: broadcast::Sender<Param>,
+    changes: broadcast::Receiver<Param>,
+}
+
+impl Parameters {
+    /// Constructs new `Parameters` for the given component, with an empty
+    /// cache.
+    ///
+    /// This function returns `Parameters` and a future. The future must be
+    /// spawned in order to update the cache, and it resolves when all of the
+    /// `Parameters` instances are dropped, or the link is closed.
+    pub fn new(component: Component) -> (Parameters, impl Future<Output = ()>) {
+        let (mut publisher, changes) = broadcast::broadcast(64);
+        publisher.set_overflow(true);
+
+        let cache = Arc::new(Mutex::new(HashMap::new()));
+        let fut = track(component.clone(), Arc::downgrade(&cache), publisher.clone());
+
+        (Parameters { component: component.sender(), cache, publisher, changes }, fut)
+    }
+
+    /// Returns the cached parameter with the given name.
+    pub fn get(&self, name: &str) -> Option<Param> {
+        self.cache.lock().unwrap().get(name).cloned()
+    }
+
+    /// Returns the cached parameters, ordered by their index.
+    pub fn all(&self) -> Vec<Param> {
+        let mut params: Vec<_> = self.cache.lock().unwrap().values().cloned().collect();
+        params.sort_by_key(|param| param.index);
+        params
+    }
+
+    /// Returns a stream of the parameters that are new to the cache, or whose
+    /// value has changed, after this call.
+    pub fn changes(&self) -> impl Stream<Item = Param> {
+        self.changes.clone()
+    }
+
+    /// Fetches every parameter of the component into the cache, see
+    /// [`Component::request_parameters`].
+    pub async fn fetch_all(&mut self) -> Result<Vec<Param>> {
+        let params = self.component.component().request_parameters().await?;
+        self.insert(params.iter().cloned());
+
+        Ok(params)
+    }
+
+    pub async fn read(&mut self, name: &str) -> Result<Param> {
+        let param = self.component.component().read_parameter(name).await?;
+        self.insert([param.clone()]);
+
+        Ok(param)
+    }
+
+    pub async fn set(&mut self, name: &str, value: ParamValue) -> Result<Param> {
+        let param = self.component.component().set_parameter(name, value).await?;
+        self.insert([param.clone()]);
+
+        Ok(param)
+    }
+
+    /// Returns the parameters of the file that differ from the cache, which
+    /// should be fetched first.
+    pub fn diff(&self, file: &ParamFile) -> Vec<ParamDiff> {
+        file.diff(self.cache.lock().unwrap().values())
+    }
+
+    /// Sets the parameters of the file that differ from the cache, which
+    /// should be fetched first, and reports the outcome of each of them.
+    ///
+    /// The values are converted to the types of the component, the ones that
+    /// do not fit exactly are not set. This only fails if the link is closed,
+    /// the parameters that could not be set are reported instead.
+    pub async fn apply(&mut self, file: &ParamFile) -> Result<ApplyReport> {
+        let mut report = ApplyReport::default();
+
+        for diff in self.diff(file) {
+            let Some(live) = diff.live else {
+                report.unknown.push(diff);
+                continue;
+            };
+
+            let Some(value) = diff.file.to_type(live.param_type()) else {
+                report.invalid.push(diff);
+                continue;
+            };
+
+            match self.set(&diff.name, value).await {
+                Ok(param) => report.applied.push(param),
+                Err(Error::Rejected) => report.rejected.push(diff),
+                Err(Error::Timeout) => report.timed_out.push(diff),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(report)
+    }
+
+    // The tracking future sees the same values, the cache is updated here so
+    // that it is up to date when the request returns.
+    fn insert(&self, params: impl IntoIterator<Item = Param>) {
+        let mut cache = self.cache.lock().unwrap();
+
+        for param in params {
+            if cache_insert(&mut cache, param.clone()) {
+                let _ = self.publisher.try_broadcast(param);
+            }
+        }
+    }
+}
+
+/// The outcome of [`Parameters::apply`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ApplyReport {
+    /// The parameters that were set, as confirmed by the component.
+    pub applied: Vec<Param>,
+    /// The parameters whose value was refused by the component.
+    pub rejected: Vec<ParamDiff>,
+    /// The parameters that were not confirmed in time.
+    pub timed_out: Vec<ParamDiff>,
+    /// The parameters that the component does not have, which are not set.
+    pub unknown: Vec<ParamDiff>,
+    /// The parameters whose value does not fit their type, such as a
+    /// fraction for an integer, which are not set.
+    pub invalid: Vec<ParamDiff>,
+}
+
+// Inserts the parameter, and returns whether it is new or has changed.
+fn cache_insert(cache: &mut HashMap<String, Param>, param: Param) -> bool {
+    match cache.get(&param.name) {
+        Some(cached) if cached.value == param.value => false,
+        _ => {
+            cache.insert(param.name.clone(), param);
+            true
+        }
+    }
+}
+
+async fn track(
+    mut component: Component,
+    cache: Weak<Mutex<HashMap<String, Param>>>,
+    publisher: broadcast::Sender<Param>,
+) {
+    let encoding = component.param_encoding();
+
+    while let Some(packet) = component.next().await {
+        // Stop when every `Parameters` is dropped.
+        let Some(cache) = cache.upgrade() else {
+            break;
+        };
+
+        let Some(param) = Param::from_packet(&packet, encoding) else {
+            continue;
+        };
+
+        if cache_insert(&mut cache.lock().unwrap(), param.clone()) {
+            let _ = publisher.try_broadcast(param);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{link::Link, wire::Frame};
+
+    #[test]
+    fn values_are_encoded_bytewise() {
+        let values = [
+            ParamValue::Uint8(200),
+            ParamValue::Int8(-3),
+            ParamValue::Uint16(40_000),
+            ParamValue::Int16(-1_234),
+            ParamValue::Uint32(u32::MAX),
+            ParamValue::Int32(-16_777_217),
+            ParamValue::Real32(0.25),
+        ];
+
+        for value in values {
+            let (raw, param_type) = value.encode(ParamEncoding::Bytewise);
+            assert_eq!(ParamValue::decode(raw, param_type, ParamEncoding::Bytewise), Some(value));
+        }
+
+        // The bytes of 1 are those of a tiny float, not 1.0.
+        let (raw, _) = ParamValue::Int32(1).encode(ParamEncoding::Bytewise);
+        assert_eq!(raw.to_bits(), 1);
+
+        let (raw, param_type) = ParamValue::Int16(-2).encode(ParamEncoding::CCast);
+        assert_eq!((raw, param_type), (-2.0, MavParamType::MAV_PARAM_TYPE_INT16));
+        assert_eq!(ParamValue::decode(raw, param_type, ParamEncoding::CCast), Some(ParamValue::Int16(-2)));
+
+        assert_eq!(ParamValue::decode(1.0, MavParamType::MAV_PARAM_TYPE_INT64, ParamEncoding::CCast), None);
+    }
+
+    #[test]
+    fn numbers_are_converted_exactly() {
+        use MavParamType::*;
+
+        assert_eq!(ParamValue::from_f64_exact(16_777_217.0, MAV_PARAM_TYPE_INT32), Some(ParamValue::Int32(16_777_217)));
+        assert_eq!(ParamValue::from_f64_exact(0.1, MAV_PARAM_TYPE_REAL32), Some(ParamValue::Real32(0.1)));
+        assert_eq!(ParamValue::from_f64_exact(1.5, MAV_PARAM_TYPE_UINT8), None);
+        assert_eq!(ParamValue::from_f64_exact(256.0, MAV_PARAM_TYPE_UINT8), None);
+        assert_eq!(ParamValue::from_f64_exact(-1.0, MAV_PARAM_TYPE_UINT32), None);
+        assert_eq!(ParamValue::from_f64_exact(1e39, MAV_PARAM_TYPE_REAL32), None);
+
+        // Unlike `from_f64`, which truncates and saturates.
+        assert_eq!(ParamValue::from_f64(256.0, MAV_PARAM_TYPE_UINT8), Some(ParamValue::Uint8(255)));
+    }
+
+    #[test]
+    fn encodings_are_picked_per_autopilot() {
+        use MavProtocolCapability as Capability;
+
+        assert_eq!(ParamEncoding::default(), ParamEncoding::CCast);
+        assert_eq!(ParamEncoding::from_autopilot(MavAutopilot::MAV_AUTOPILOT_PX4), ParamEncoding::Bytewise);
+        assert_eq!(ParamEncoding::from_autopilot(MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA), ParamEncoding::CCast);
+
+        let bytewise = Capability::MAV_PROTOCOL_CAPABILITY_MAVLINK2 | Capability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_BYTEWISE;
+        assert_eq!(ParamEncoding::from_capabilities(bytewise), Some(ParamEncoding::Bytewise));
+        assert_eq!(
+            ParamEncoding::from_capabilities(Capability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_C_CAST),
+            Some(ParamEncoding::CCast)
+        );
+        assert_eq!(ParamEncoding::from_capabilities(Capability::MAV_PROTOCOL_CAPABILITY_MAVLINK2), None);
+    }
+
+    #[test]
+    fn names_are_padded_and_truncated() {
+        assert_eq!(param_name(&param_id("SYSID_THISMAV")), "SYSID_THISMAV");
+        assert_eq!(param_name(&param_id("A_NAME_THAT_IS_TOO_LONG")), "A_NAME_THAT_IS_T");
+    }
+
+    // An autopilot that loses the first response to PARAM_REQUEST_LIST, and
+    // refuses to change `RO`.
+    async fn autopilot(requests: flume::Receiver<Frame>, responses: flume::Sender<Packet>) {
+        let mut params = vec![
+            ("RO", ParamValue::Real32(1.0)),
+            ("SPEED", ParamValue::Real32(5.0)),
+            ("ALT", ParamValue::Real32(10.0)),
+            ("FLAGS", ParamValue::Uint8(0)),
+        ];
+
+        let value = |params: &[(&str, ParamValue)], index: usize| {
+            let (name, value) = params[index];
+            let (param_value, param_type) = value.encode(ParamEncoding::CCast);
+
+            Packet {
+                header: Header { system_id: 1, component_id: 1, sequence: 0 },
+                message: Message::PARAM_VALUE(PARAM_VALUE_DATA {
+                    param_value,
+                    param_count: params.len() as u16,
+                    param_index: index as u16,
+                    param_id: param_id(name),
+                    param_type,
+                }),
+                version: Version::V2,
+            }
+        };
+
+        while let Ok(Frame::Packet(request)) = requests.recv_async().await {
+            let index = match request.message {
+                Message::PARAM_REQUEST_LIST(_) => {
+                    for index in 1..params.len() {
+                        responses.send(value(&params, index)).unwrap();
+                    }
+
+                    continue;
+                }
+                Message::PARAM_REQUEST_READ(read) if read.param_index >= 0 => read.param_index as usize,
+                Message::PARAM_REQUEST_READ(read) => {
+                    let name = param_name(&read.param_id);
+                    params.iter().position(|(param, _)| *param == name).unwrap()
+                }
+                Message::PARAM_SET(set) => {
+                    let name = param_name(&set.param_id);
+                    let index = params.iter().position(|(param, _)| *param == name).unwrap();
+
+                    if name != "RO" {
+                        let value = ParamValue::decode(set.param_value, set.param_type, ParamEncoding::CCast);
+                        params[index].1 = value.unwrap();
+                    }
+
+                    index
+                }
+                _ => continue,
+            };
+
+            responses.send(value(&params, index)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn parameters_are_fetched_and_set() {
+        let (requests, requests_receiver) = flume::unbounded();
+        let (responses_sender, responses) = flume::unbounded();
+        let (link, connection) = Link::new(requests.into_sink(), responses.into_stream(), 255, 190);
+        let (mut parameters, tracking) = Parameters::new(Component::new(1, 1, link));
+        let mut changes = Box::pin(parameters.changes());
+
+        tokio::spawn(connection);
+        tokio::spawn(tracking);
+        tokio::spawn(autopilot(requests_receiver, responses_sender));
+
+        // The missing parameter is read by its index.
+        let params = parameters.fetch_all().await.unwrap();
+        let names: Vec<_> = params.iter().map(|param| param.name.as_str()).collect();
+        assert_eq!(names, ["RO", "SPEED", "ALT", "FLAGS"]);
+        assert_eq!(parameters.get("ALT").unwrap().value, ParamValue::Real32(10.0));
+
+        let param = parameters.set("SPEED", ParamValue::Real32(7.5)).await.unwrap();
+        assert_eq!((param.index, param.value), (1, ParamValue::Real32(7.5)));
+        assert_eq!(parameters.get("SPEED").unwrap().value, ParamValue::Real32(7.5));
+
+        let result = parameters.set("RO", ParamValue::Real32(2.0)).await;
+        assert!(matches!(result, Err(Error::Rejected)));
+        assert_eq!(parameters.read("RO").await.unwrap().value, ParamValue::Real32(1.0));
+
+        // Every parameter is new, then only `SPEED` changes.
+        let changes: Vec<_> = changes.by_ref().take(5).map(|param| (param.name, param.value)).collect().await;
+        assert_eq!(changes.last().unwrap(), &("SPEED".to_string(), ParamValue::Real32(7.5)));
+    }
+
+    #[tokio::test]
+    async fn files_are_applied() {
+        let (requests, requests_receiver) = flume::unbounded();
+        let (responses_sender, responses) = flume::unbounded();
+        let (link, connection) = Link::new(requests.into_sink(), responses.into_stream(), 255, 190);
+        let (mut parameters, tracking) = Parameters::new(Component::new(1, 1, link));
+
+        tokio::spawn(connection);
+        tokio::spawn(tracking);
+        tokio::spawn(autopilot(requests_receiver, responses_sender));
+
+        parameters.fetch_all().await.unwrap();
+
+        let file = ParamFile::parse("ALT,10\nRO,3\nSPEED,8\nGONE,1\nFLAGS,1.5\n", ParamFormat::MissionPlanner).unwrap();
+        let report = parameters.apply(&file).await.unwrap();
+
+        let names = |diffs: &[ParamDiff]| diffs.iter().map(|diff| diff.name.clone()).collect::<Vec<_>>();
+        assert_eq!(report.applied.iter().map(|param| param.name.as_str()).collect::<Vec<_>>(), ["SPEED"]);
+        assert_eq!((names(&report.rejected), names(&report.unknown)), (vec!["RO".to_string()], vec!["GONE".to_string()]));
+        assert_eq!(names(&report.invalid), ["FLAGS"]);
+        assert!(report.timed_out.is_empty());
+
+        // Only the parameters that are still different are left.
+        let names: Vec<_> = parameters.diff(&file).into_iter().map(|diff| diff.name).collect();
+        assert_eq!(names, ["FLAGS", "GONE", "RO"]);
+    }
+}
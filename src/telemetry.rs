@@ -0,0 +1,362 @@
+//! The latest telemetry of a component.
+//!
+//! [`Telemetry`] keeps the latest state that a component reported, which can
+//! be read as a [`TelemetryState`] snapshot, or watched field by field.
+use crate::{
+    component::Component,
+    dialect::*,
+};
+
+use futures_util::{future::Future, stream, Stream, StreamExt};
+use tokio::sync::watch;
+
+/// A position on the globe.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Position {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    /// The altitude above the mean sea level.
+    pub absolute_altitude_m: f32,
+    /// The altitude above the home position.
+    pub relative_altitude_m: f32,
+}
+
+/// A position, or a velocity, in the local north-east-down frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Ned {
+    pub north: f32,
+    pub east: f32,
+    pub down: f32,
+}
+
+/// The position and velocity in the local frame, in meters and m/s.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LocalPosition {
+    pub position_m: Ned,
+    pub velocity_m_s: Ned,
+}
+
+/// The attitude and angular speeds, in radians and rad/s.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Attitude {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll_speed: f32,
+    pub pitch_speed: f32,
+    pub yaw_speed: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsInfo {
+    pub fix_type: GpsFixType,
+    pub satellites_visible: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Battery {
+    pub voltage_v: f32,
+    /// The remaining capacity, or `None` if the autopilot does not know it.
+    pub remaining_percent: Option<f32>,
+}
+
+impl From<&SYS_STATUS_DATA> for Battery {
+    fn from(status: &SYS_STATUS_DATA) -> Self {
+        Self {
+            voltage_v: status.voltage_battery as f32 / 1000.0,
+            remaining_percent: (status.battery_remaining >= 0)
+                .then_some(status.battery_remaining as f32),
+        }
+    }
+}
+
+/// The flight mode, whose custom mode is specific to the autopilot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlightMode {
+    pub base_mode: MavModeFlag,
+    pub custom_mode: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RcStatus {
+    pub channel_count: u8,
+    /// The signal strength, or `None` if the receiver does not report it.
+    pub signal_strength_percent: Option<f32>,
+}
+
+/// The latest telemetry of a component, each field is `None` until its
+/// message is received.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TelemetryState {
+    pub position: Option<Position>,
+    pub local_position: Option<LocalPosition>,
+    pub attitude: Option<Attitude>,
+    /// The velocity in the north-east-down frame, in m/s.
+    pub velocity: Option<Ned>,
+    pub gps_info: Option<GpsInfo>,
+    pub battery: Option<Battery>,
+    pub flight_mode: Option<FlightMode>,
+    pub armed: Option<bool>,
+    pub home: Option<Position>,
+    pub rc_status: Option<RcStatus>,
+}
+
+impl TelemetryState {
+    /// Updates the state with the given message, and returns whether the
+    /// message carries telemetry.
+    pub fn update(&mut self, message: &Message) -> bool {
+        match message {
+            Message::GLOBAL_POSITION_INT(data) => {
+                self.position = Some(Position {
+                    latitude_deg: data.lat as f64 * 1e-7,
+                    longitude_deg: data.lon as f64 * 1e-7,
+                    absolute_altitude_m: data.alt as f32 / 1000.0,
+                    relative_altitude_m: data.relative_alt as f32 / 1000.0,
+                });
+
+                self.velocity = Some(Ned {
+                    north: data.vx as f32 / 100.0,
+                    east: data.vy as f32 / 100.0,
+                    down: data.vz as f32 / 100.0,
+                });
+            }
+            Message::LOCAL_POSITION_NED(data) => {
+                self.local_position = Some(LocalPosition {
+                    position_m: Ned { north: data.x, east: data.y, down: data.z },
+                    velocity_m_s: Ned { north: data.vx, east: data.vy, down: data.vz },
+                });
+            }
+            Message::ATTITUDE(data) => {
+                self.attitude = Some(Attitude {
+                    roll: data.roll,
+                    pitch: data.pitch,
+                    yaw: data.yaw,
+                    roll_speed: data.rollspeed,
+                    pitch_speed: data.pitchspeed,
+                    yaw_speed: data.yawspeed,
+                });
+            }
+            Message::GPS_RAW_INT(data) => {
+                self.gps_info = Some(GpsInfo {
+                    fix_type: data.fix_type,
+                    satellites_visible: data.satellites_visible,
+                });
+            }
+            Message::SYS_STATUS(data) => self.battery = Some(data.into()),
+            Message::HEARTBEAT(data) => {
+                self.flight_mode = Some(FlightMode {
+                    base_mode: data.base_mode,
+                    custom_mode: data.custom_mode,
+                });
+
+                self.armed = Some(data.base_mode.contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED));
+            }
+            Message::HOME_POSITION(data) => {
+                self.home = Some(Position {
+                    latitude_deg: data.latitude as f64 * 1e-7,
+                    longitude_deg: data.longitude as f64 * 1e-7,
+                    absolute_altitude_m: data.altitude as f32 / 1000.0,
+                    relative_altitude_m: 0.0,
+                });
+            }
+            Message::RC_CHANNELS(data) => {
+                self.rc_status = Some(RcStatus {
+                    channel_count: data.chancount,
+                    // 255 stands for an unknown signal strength.
+                    signal_strength_percent: (data.rssi != u8::MAX)
+                        .then_some(data.rssi as f32 * 100.0 / 254.0),
+                });
+            }
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// The latest telemetry of a component, see the [module
+/// documentation](self).
+///
+/// Every field can be watched with a stream that yields its current value,
+/// if there is one, and then every value that differs from the previous.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    state: watch::Receiver<TelemetryState>,
+}
+
+impl Telemetry {
+    /// Constructs a new `Telemetry` for the given component.
+    ///
+    /// This function returns a `Telemetry` and a future. The future must be
+    /// spawned in order to update the telemetry, and it resolves when all of
+    /// the `Telemetry` instances are dropped, or the link is closed.
+    pub fn new(component: Component) -> (Telemetry, impl Future<Output = ()>) {
+        let (sender, state) = watch::channel(TelemetryState::default());
+        let fut = track(component, sender);
+
+        (Telemetry { state }, fut)
+    }
+
+    pub fn snapshot(&self) -> TelemetryState {
+        self.state.borrow().clone()
+    }
+
+    /// Returns a stream of the values of the given field of the state.
+    pub fn watch<T, F>(&self, field: F) -> impl Stream<Item = T>
+    where
+        T: Clone + PartialEq,
+        F: Fn(&TelemetryState) -> Option<T>,
+    {
+        let mut state = self.state.clone();
+
+        // Yield the current value first.
+        state.mark_changed();
+
+        stream::unfold((state, None, field), |(mut state, last, field)| async move {
+            loop {
+                state.changed().await.ok()?;
+                let value = field(&state.borrow_and_update());
+
+                match value {
+                    Some(value) if last.as_ref() != Some(&value) => {
+                        break Some((value.clone(), (state, Some(value), field)))
+                    }
+                    _ => {}
+                }
+            }
+        })
+    }
+
+    pub fn position(&self) -> impl Stream<Item = Position> {
+        self.watch(|state| state.position)
+    }
+
+    pub fn local_position(&self) -> impl Stream<Item = LocalPosition> {
+        self.watch(|state| state.local_position)
+    }
+
+    pub fn attitude(&self) -> impl Stream<Item = Attitude> {
+        self.watch(|state| state.attitude)
+    }
+
+    pub fn velocity(&self) -> impl Stream<Item = Ned> {
+        self.watch(|state| state.velocity)
+    }
+
+    pub fn gps_info(&self) -> impl Stream<Item = GpsInfo> {
+        self.watch(|state| state.gps_info)
+    }
+
+    pub fn battery(&self) -> impl Stream<Item = Battery> {
+        self.watch(|state| state.battery)
+    }
+
+    pub fn flight_mode(&self) -> impl Stream<Item = FlightMode> {
+        self.watch(|state| state.flight_mode)
+    }
+
+    pub fn armed(&self) -> impl Stream<Item = bool> {
+        self.watch(|state| state.armed)
+    }
+
+    pub fn home(&self) -> impl Stream<Item = Position> {
+        self.watch(|state| state.home)
+    }
+
+    pub fn rc_status(&self) -> impl Stream<Item = RcStatus> {
+        self.watch(|state| state.rc_status)
+    }
+}
+
+async fn track(mut component: Component, sender: watch::Sender<TelemetryState>) {
+    while let Some(packet) = component.next().await {
+        // Stop when every `Telemetry` is dropped.
+        if sender.is_closed() {
+            break;
+        }
+
+        sender.send_if_modified(|state| state.update(&packet.message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{link::Link, wire::Packet};
+
+    #[test]
+    fn state_decodes_telemetry() {
+        let mut state = TelemetryState::default();
+
+        assert!(state.update(&Message::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+            lat: 383706171,
+            lon: 272008103,
+            alt: 105_000,
+            relative_alt: 5_000,
+            vx: 150,
+            ..Default::default()
+        })));
+
+        let position = state.position.unwrap();
+        assert!((position.latitude_deg - 38.3706171).abs() < 1e-9);
+        assert!((position.longitude_deg - 27.2008103).abs() < 1e-9);
+        assert_eq!((position.absolute_altitude_m, position.relative_altitude_m), (105.0, 5.0));
+        assert_eq!(state.velocity.unwrap().north, 1.5);
+
+        assert!(state.update(&Message::SYS_STATUS(SYS_STATUS_DATA {
+            voltage_battery: 12_600,
+            battery_remaining: -1,
+            ..Default::default()
+        })));
+
+        assert_eq!(state.battery, Some(Battery { voltage_v: 12.6, remaining_percent: None }));
+
+        assert!(state.update(&Message::HEARTBEAT(HEARTBEAT_DATA {
+            base_mode: MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED,
+            custom_mode: 4,
+            ..Default::default()
+        })));
+
+        assert_eq!(state.armed, Some(true));
+        assert_eq!(state.flight_mode.unwrap().custom_mode, 4);
+
+        assert!(!state.update(&Message::STATUSTEXT(Default::default())));
+    }
+
+    #[tokio::test]
+    async fn telemetry_watches_fields() {
+        let header = Header { system_id: 1, component_id: 1, sequence: 0 };
+        let heartbeat = |base_mode| Packet {
+            header,
+            message: Message::HEARTBEAT(HEARTBEAT_DATA { base_mode, ..Default::default() }),
+            version: Version::V2,
+        };
+
+        let (sender, receiver) = flume::unbounded();
+        let (link, connection) = Link::new(futures::sink::drain(), receiver.into_stream(), 255, 190);
+        let (telemetry, tracking) = Telemetry::new(Component::new(1, 1, link));
+        let mut armed = Box::pin(telemetry.armed());
+
+        tokio::spawn(connection);
+        tokio::spawn(tracking);
+
+        let flags = MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED;
+
+        sender.send(heartbeat(MavModeFlag::empty())).unwrap();
+        assert_eq!(armed.next().await, Some(false));
+
+        // Only the changes of the field are yielded.
+        sender.send(heartbeat(MavModeFlag::empty())).unwrap();
+        sender.send(heartbeat(flags)).unwrap();
+        assert_eq!(armed.next().await, Some(true));
+
+        sender.send(heartbeat(flags | MavModeFlag::MAV_MODE_FLAG_GUIDED_ENABLED)).unwrap();
+        sender.send(heartbeat(MavModeFlag::empty())).unwrap();
+        assert_eq!(armed.next().await, Some(false));
+
+        // The streams end with the link.
+        drop(sender);
+        assert_eq!(armed.next().await, None);
+        assert_eq!(telemetry.snapshot().armed, Some(false));
+    }
+}